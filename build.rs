@@ -1,8 +1,15 @@
-use std::{env, fs, path::PathBuf};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
 
 use glob::glob;
 use mindy::parser::{LogicParser, parse_and_serialize_ast};
 
+const DEFAULT_HEAP_SIZE: usize = 64 * 1024;
+// enough for the VM and the buildings to start up
+const MIN_HEAP_SIZE: usize = 16 * 1024;
+
 fn main() {
     println!("cargo:rerun-if-changed=build.rs");
 
@@ -13,6 +20,8 @@ fn main() {
     let mlog_dir = out_dir.join("mlog");
     fs::create_dir(&mlog_dir).ok(); // ignore error if directory already exists
 
+    let mut programs = Vec::new();
+
     let parser = LogicParser::new();
     for path in glob("src/**/*.mlog").unwrap().flatten() {
         println!("cargo:rerun-if-changed={}", path.display());
@@ -22,8 +31,80 @@ fn main() {
 
         let out = mlog_dir.join(path.with_extension("bin").file_name().unwrap());
         fs::write(&out, ast).unwrap();
+
+        programs.push((
+            path.file_stem().unwrap().to_string_lossy().into_owned(),
+            parse_rotation(&path, &code),
+            parse_heap_size(&path, &code),
+        ));
     }
 
+    // select the program to run
+
+    programs.sort();
+
+    let selected = programs
+        .iter()
+        .map(|(name, _, _)| name)
+        .filter(|name| env::var_os(format!("CARGO_FEATURE_{}", name.to_uppercase())).is_some())
+        .collect::<Vec<_>>();
+
+    let program = match selected[..] {
+        [name] => Ok(name.as_str()),
+        [] if env::var_os("CARGO_FEATURE_DEFAULT_PROGRAM").is_some() => Ok("hello"),
+        [] => Err(format!(
+            "no program selected, enable one of these features: {} (or enable default_program to run hello)",
+            programs
+                .iter()
+                .map(|(name, _, _)| name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+        _ => Err(format!(
+            "only one program can be selected, but got multiple: {}",
+            selected
+                .iter()
+                .map(|name| name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    };
+
+    let mut program_rs = match program {
+        Ok(name) => {
+            let index = programs.iter().position(|(n, _, _)| n == name).unwrap();
+            format!(
+                "const DEFAULT_PROGRAM: usize = {index};\n\
+                 const HEAP_SIZE: usize = {};\n",
+                programs[index].2
+            )
+        }
+        // define dummy values so the only error is the one we want to show
+        Err(message) => format!(
+            "compile_error!({message:?});\n\
+             const DEFAULT_PROGRAM: usize = 0;\n\
+             const HEAP_SIZE: usize = {DEFAULT_HEAP_SIZE};\n"
+        ),
+    };
+
+    // embed every program so mlog can switch between them at runtime
+    program_rs.push_str("const PROGRAMS: &[Program] = &[\n");
+    for (name, rotation, heap_size) in &programs {
+        let global = format!("@program{}", to_pascal_case(name));
+        program_rs.push_str(&format!(
+            "    Program {{ name: \"{name}\", global: \"{global}\", ast_bytes: include_bytes!(concat!(env!(\"OUT_DIR\"), \"/mlog/{name}.bin\")), rotation: Rotation::{rotation}, heap_size: {heap_size} }},\n"
+        ));
+    }
+    program_rs.push_str("];\n");
+
+    fs::write(out_dir.join("program.rs"), program_rs).unwrap();
+
+    #[cfg(feature = "sprites")]
+    fs::write(out_dir.join("sprites.rs"), pack_sprites()).unwrap();
+
+    #[cfg(feature = "proportional_text")]
+    fs::write(out_dir.join("glyph_ink.rs"), measure_glyph_ink()).unwrap();
+
     // set up embassy memory.x
 
     println!("cargo:rerun-if-changed=memory-pico1.x");
@@ -44,3 +125,205 @@ fn main() {
     #[cfg(feature = "pico1")]
     println!("cargo:rustc-link-arg-bins=-Tlink-rp.x");
 }
+
+/// Programs can choose the display orientation with a comment like `# orientation: portrait`.
+fn parse_rotation(path: &Path, code: &str) -> &'static str {
+    let orientation = code
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("# orientation:"))
+        .map(str::trim)
+        .unwrap_or("landscape");
+
+    match orientation {
+        "portrait" => "Deg0",
+        "landscape" => "Deg90",
+        "portrait_flipped" => "Deg180",
+        "landscape_flipped" => "Deg270",
+        _ => panic!(
+            "{}: unknown orientation {orientation:?}, expected one of: portrait, landscape, portrait_flipped, landscape_flipped",
+            path.display()
+        ),
+    }
+}
+
+/// Programs can ask for a bigger or smaller heap with a comment like `# heap: 96K`, in bytes or
+/// KB. The heap is allocated statically for the program selected at build time, and programs that
+/// need more than that can't be switched to at runtime.
+fn parse_heap_size(path: &Path, code: &str) -> usize {
+    let Some(size) = code
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("# heap:"))
+        .map(str::trim)
+    else {
+        return DEFAULT_HEAP_SIZE;
+    };
+
+    let bytes = match size.strip_suffix(['K', 'k']) {
+        Some(kb) => kb.trim().parse::<usize>().map(|kb| kb * 1024),
+        None => size.parse(),
+    };
+    match bytes {
+        Ok(bytes) if bytes >= MIN_HEAP_SIZE => bytes,
+        _ => panic!(
+            "{}: invalid heap size {size:?}, expected a number of bytes (or KB with a K suffix) of at least {}K",
+            path.display(),
+            MIN_HEAP_SIZE / 1024,
+        ),
+    }
+}
+
+/// Cuts `src/sprites.png` into sprites of `SPRITE_WIDTH` by `SPRITE_HEIGHT` pixels, numbered from
+/// the top left across each row, and RLE-encodes each one like `bank1` images, as Rust source
+/// for `src/sprites.rs`.
+#[cfg(feature = "sprites")]
+fn pack_sprites() -> String {
+    const PATH: &str = "src/sprites.png";
+    println!("cargo:rerun-if-changed={PATH}");
+
+    let env_size = |name: &str| -> usize {
+        println!("cargo:rerun-if-env-changed={name}");
+        let value = env::var(name).unwrap_or_default();
+        match value.parse() {
+            Ok(size) if size > 0 => size,
+            _ => panic!("{name} must be a positive number, but got {value:?}"),
+        }
+    };
+    let (width, height) = (env_size("SPRITE_WIDTH"), env_size("SPRITE_HEIGHT"));
+
+    let file = fs::File::open(PATH)
+        .unwrap_or_else(|err| panic!("{PATH}: {err} (the sprites feature needs a sprite sheet)"));
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().unwrap();
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).unwrap();
+
+    let (sheet_width, sheet_height) = (info.width as usize, info.height as usize);
+    if sheet_width % width != 0 || sheet_height % height != 0 {
+        panic!(
+            "{PATH}: {sheet_width}x{sheet_height} isn't a whole number of {width}x{height} sprites"
+        );
+    }
+
+    // packed like Mindustry's colors, with pixels below half alpha transparent
+    let channels = info.color_type.samples();
+    let pixel = |x: usize, y: usize| -> u32 {
+        let i = y * info.line_size + x * channels;
+        let [r, g, b, a] = match info.color_type {
+            png::ColorType::Grayscale => [buf[i], buf[i], buf[i], 255],
+            png::ColorType::GrayscaleAlpha => [buf[i], buf[i], buf[i], buf[i + 1]],
+            png::ColorType::Rgb => [buf[i], buf[i + 1], buf[i + 2], 255],
+            png::ColorType::Rgba => [buf[i], buf[i + 1], buf[i + 2], buf[i + 3]],
+            png::ColorType::Indexed => unreachable!("palettes are expanded by the decoder"),
+        };
+        if a < 128 {
+            0
+        } else {
+            u32::from_be_bytes([r, g, b, 255])
+        }
+    };
+
+    let mut sprites_rs = format!(
+        "pub const SPRITE_WIDTH: u32 = {width};\n\
+         pub static SPRITES: &[&[(u16, u32)]] = &[\n"
+    );
+    for top in (0..sheet_height).step_by(height) {
+        for left in (0..sheet_width).step_by(width) {
+            // rows go from the bottom up, like logic coordinates
+            let mut runs: Vec<(u16, u32)> = Vec::new();
+            for y in (top..top + height).rev() {
+                for x in left..left + width {
+                    let color = pixel(x, y);
+                    match runs.last_mut() {
+                        Some((length, last)) if *last == color && *length < u16::MAX => {
+                            *length += 1
+                        }
+                        _ => runs.push((1, color)),
+                    }
+                }
+            }
+            let runs = runs
+                .iter()
+                .map(|(length, color)| format!("({length}, {color:#010x})"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            sprites_rs.push_str(&format!("    &[{runs}],\n"));
+        }
+    }
+    sprites_rs.push_str("];\n");
+    sprites_rs
+}
+
+/// Measures which columns of each printable ASCII glyph in FONT_6X13 have ink, as Rust source for
+/// `src/proportional_text.rs`.
+#[cfg(feature = "proportional_text")]
+fn measure_glyph_ink() -> String {
+    use std::convert::Infallible;
+
+    use embedded_graphics::{
+        mono_font::{MonoTextStyle, ascii::FONT_6X13},
+        pixelcolor::BinaryColor,
+        prelude::*,
+        text::{Baseline, Text},
+    };
+
+    // columns given to glyphs without any ink, ie. space
+    const BLANK_WIDTH: i32 = 2;
+
+    /// Records the leftmost and rightmost columns that are drawn to.
+    struct Ink(Option<(i32, i32)>);
+
+    impl OriginDimensions for Ink {
+        fn size(&self) -> Size {
+            FONT_6X13.character_size
+        }
+    }
+
+    impl DrawTarget for Ink {
+        type Color = BinaryColor;
+        type Error = Infallible;
+
+        fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+        where
+            I: IntoIterator<Item = Pixel<Self::Color>>,
+        {
+            for Pixel(point, color) in pixels {
+                if color.is_on() {
+                    let (left, right) = self.0.get_or_insert((point.x, point.x));
+                    *left = (*left).min(point.x);
+                    *right = (*right).max(point.x);
+                }
+            }
+            Ok(())
+        }
+    }
+
+    let style = MonoTextStyle::new(&FONT_6X13, BinaryColor::On);
+    let mut glyph_ink_rs = String::from("static GLYPH_INK: [(u8, u8); 95] = [\n");
+    for c in ' '..='~' {
+        let mut ink = Ink(None);
+        Text::with_baseline(&c.to_string(), Point::zero(), style, Baseline::Top)
+            .draw(&mut ink)
+            .unwrap();
+        let (left, width) = match ink.0 {
+            Some((left, right)) => (left, right - left + 1),
+            None => (0, BLANK_WIDTH),
+        };
+        glyph_ink_rs.push_str(&format!("    ({left}, {width}), // {c:?}\n"));
+    }
+    glyph_ink_rs.push_str("];\n");
+    glyph_ink_rs
+}
+
+fn to_pascal_case(name: &str) -> String {
+    name.split('_')
+        .flat_map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|c| c.to_ascii_uppercase())
+                .into_iter()
+                .chain(chars)
+        })
+        .collect()
+}
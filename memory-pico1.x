@@ -19,3 +19,7 @@ MEMORY {
 
 _panic_dump_start = ORIGIN(PANIC);
 _panic_dump_end   = ORIGIN(PANIC) + LENGTH(PANIC);
+
+/* for the flash building */
+_flash_used_end = LOADADDR(.data) + SIZEOF(.data);
+_flash_end      = ORIGIN(FLASH) + LENGTH(FLASH);
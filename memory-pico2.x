@@ -25,6 +25,10 @@ MEMORY {
 _panic_dump_start = ORIGIN(PANIC);
 _panic_dump_end   = ORIGIN(PANIC) + LENGTH(PANIC);
 
+/* for the flash building */
+_flash_used_end = __end_block_addr + SIZEOF(.end_block);
+_flash_end      = ORIGIN(FLASH) + LENGTH(FLASH);
+
 SECTIONS {
     /* ### Boot ROM info
      *
@@ -0,0 +1,48 @@
+use embassy_rp::pwm::{self, Pwm};
+
+/// Display backlight driven by PWM on channel B of its slice.
+pub struct Backlight<'a> {
+    pwm: Pwm<'a>,
+    config: pwm::Config,
+    brightness: f64,
+}
+
+impl<'a> Backlight<'a> {
+    /// Creates a backlight that starts off.
+    pub fn new(pwm: Pwm<'a>) -> Self {
+        let mut config = pwm::Config::default();
+        // ~12.5kHz at 125MHz, and lets compare_b go one past top for 100% duty cycle
+        config.top = 9_999;
+
+        let mut backlight = Self {
+            pwm,
+            config,
+            brightness: 0.,
+        };
+        backlight.apply(0.);
+        backlight
+    }
+
+    pub fn brightness(&self) -> f64 {
+        self.brightness
+    }
+
+    pub fn set_brightness(&mut self, brightness: f64) {
+        self.brightness = brightness.clamp(0., 1.);
+        self.apply(self.brightness);
+    }
+
+    /// Temporarily turns off the backlight without changing its brightness.
+    pub fn blank(&mut self) {
+        self.apply(0.);
+    }
+
+    pub fn unblank(&mut self) {
+        self.apply(self.brightness);
+    }
+
+    fn apply(&mut self, duty: f64) {
+        self.config.compare_b = (duty * (self.config.top as f64 + 1.)) as u16;
+        self.pwm.set_config(&self.config);
+    }
+}
@@ -0,0 +1,91 @@
+use alloc::rc::Rc;
+use core::cell::Cell;
+
+use embedded_graphics::{Pixel, prelude::*, primitives::Rectangle};
+
+/// Shared count of the pixels drawn outside of a [`BoundsCheckedDrawTarget`].
+#[derive(Clone, Default)]
+pub struct OutOfBoundsCounter(Rc<Cell<u64>>);
+
+impl OutOfBoundsCounter {
+    pub fn get(&self) -> u64 {
+        self.0.get()
+    }
+
+    fn add(&self, pixels: u64) {
+        self.0.set(self.0.get() + pixels);
+    }
+}
+
+/// Draw target wrapper that discards and counts pixels outside of the display, instead of passing
+/// them to the display.
+pub struct BoundsCheckedDrawTarget<D> {
+    inner: D,
+    counter: OutOfBoundsCounter,
+}
+
+impl<D> BoundsCheckedDrawTarget<D> {
+    pub fn new(inner: D) -> (Self, OutOfBoundsCounter) {
+        let counter = OutOfBoundsCounter::default();
+        (
+            Self {
+                inner,
+                counter: counter.clone(),
+            },
+            counter,
+        )
+    }
+}
+
+impl<D: Dimensions> Dimensions for BoundsCheckedDrawTarget<D> {
+    fn bounding_box(&self) -> Rectangle {
+        self.inner.bounding_box()
+    }
+}
+
+impl<D: DrawTarget> DrawTarget for BoundsCheckedDrawTarget<D> {
+    type Color = D::Color;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let bounds = self.inner.bounding_box();
+        let counter = &self.counter;
+        self.inner
+            .draw_iter(pixels.into_iter().filter(|Pixel(point, _)| {
+                let inside = bounds.contains(*point);
+                if !inside {
+                    counter.add(1);
+                }
+                inside
+            }))
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        if self.inner.bounding_box().intersection(area) == *area {
+            self.inner.fill_contiguous(area, colors)
+        } else {
+            self.draw_iter(
+                area.points()
+                    .zip(colors)
+                    .map(|(point, color)| Pixel(point, color)),
+            )
+        }
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let inside = self.inner.bounding_box().intersection(area);
+        let pixels = |rect: Rectangle| rect.size.width as u64 * rect.size.height as u64;
+        self.counter.add(pixels(*area) - pixels(inside));
+        self.inner.fill_solid(&inside, color)
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.inner.clear(color)
+    }
+}
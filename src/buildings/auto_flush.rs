@@ -0,0 +1,50 @@
+use alloc::rc::Rc;
+use core::cell::{Cell, RefCell};
+
+use mindy::vm::ProcessorState;
+use widestring::{U16Str, U16String};
+
+use super::serial::{TxQueue, queue_tx};
+
+/// Line-buffered output for a building's print buffer, so printed lines are sent as soon as they
+/// end with a newline instead of waiting for `printflush`.
+#[derive(Clone)]
+pub struct AutoFlush {
+    enabled: Rc<Cell<bool>>,
+    tx_buf: Rc<RefCell<TxQueue>>,
+}
+
+impl AutoFlush {
+    pub(super) fn new(tx_buf: Rc<RefCell<TxQueue>>) -> Self {
+        Self {
+            enabled: Rc::new(Cell::new(false)),
+            tx_buf,
+        }
+    }
+
+    pub(super) fn set_enabled(&self, enabled: bool) {
+        self.enabled.set(enabled);
+    }
+}
+
+/// Moves all complete lines from the print buffer to the buildings that have auto flush enabled.
+///
+/// This should be called from the instruction hook, since buildings don't see `print`.
+pub fn auto_flush_lines(targets: &[AutoFlush], state: &mut ProcessorState) {
+    let targets = targets.iter().filter(|target| target.enabled.get());
+    if targets.clone().next().is_none() {
+        return;
+    }
+
+    let buffer = state.printbuffer.as_slice();
+    let Some(end) = buffer.iter().rposition(|&c| c == b'\n' as u16) else {
+        return;
+    };
+
+    let lines = U16Str::from_slice(&buffer[..=end]).to_string_lossy();
+    for target in targets {
+        queue_tx(&target.tx_buf, &lines);
+    }
+
+    state.printbuffer = U16String::from_vec(buffer[end + 1..].to_vec());
+}
@@ -0,0 +1,55 @@
+use alloc::{rc::Rc, vec::Vec};
+use core::{
+    cell::{Cell, RefCell},
+    mem,
+};
+
+#[derive(Default)]
+struct BridgeState {
+    enabled: Cell<bool>,
+    to_uart: RefCell<Vec<u8>>,
+    to_serial: RefCell<Vec<u8>>,
+}
+
+/// Shared state for forwarding bytes between `uart0` and `serial` without going through mlog, so
+/// the Pico can act as a USB to serial adapter.
+///
+/// While enabled, received bytes are forwarded instead of being readable from mlog.
+#[derive(Clone, Default)]
+pub struct Bridge(Rc<BridgeState>);
+
+impl Bridge {
+    pub(super) fn enabled(&self) -> bool {
+        self.0.enabled.get()
+    }
+
+    pub(super) fn set_enabled(&self, enabled: bool) {
+        self.0.enabled.set(enabled);
+        self.0.to_uart.borrow_mut().clear();
+        self.0.to_serial.borrow_mut().clear();
+    }
+
+    pub(super) fn push_to_uart(&self, data: &[u8]) {
+        self.0.to_uart.borrow_mut().extend_from_slice(data);
+    }
+
+    pub(super) fn has_pending_to_uart(&self) -> bool {
+        !self.0.to_uart.borrow().is_empty()
+    }
+
+    pub(super) fn take_to_uart(&self) -> Vec<u8> {
+        mem::take(&mut *self.0.to_uart.borrow_mut())
+    }
+
+    pub(super) fn push_to_serial(&self, data: &[u8]) {
+        self.0.to_serial.borrow_mut().extend_from_slice(data);
+    }
+
+    pub(super) fn has_pending_to_serial(&self) -> bool {
+        !self.0.to_serial.borrow().is_empty()
+    }
+
+    pub(super) fn take_to_serial(&self) -> Vec<u8> {
+        mem::take(&mut *self.0.to_serial.borrow_mut())
+    }
+}
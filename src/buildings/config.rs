@@ -0,0 +1,131 @@
+use mindy::vm::{LValue, LVar};
+use widestring::{U16Str, u16str};
+
+/// Defines an enum whose variants are exposed to mlog as global constants.
+macro_rules! global_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $ty:ident($to_value:expr) {
+            $($variant:ident = $name:literal,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum $ty {
+            $($variant,)*
+        }
+
+        impl $ty {
+            const ALL: &[Self] = &[$(Self::$variant,)*];
+
+            pub fn globals() -> impl Iterator<Item = (&'static U16Str, LVar)> {
+                let to_value: fn(usize) -> f64 = $to_value;
+                [$((u16str!($name), LVar::Constant(to_value(Self::$variant as usize).into())),)*]
+                    .into_iter()
+            }
+
+            pub fn from_lvalue(value: &LValue) -> Option<Self> {
+                let to_value: fn(usize) -> f64 = $to_value;
+                Self::ALL
+                    .iter()
+                    .find(|&&variant| to_value(variant as usize) == value.num())
+                    .copied()
+            }
+        }
+    };
+}
+
+global_enum! {
+    /// Options for `control config <building> <option> <value>`.
+    pub enum ConfigOption(|i| i as f64) {
+        AutoClear = "@autoClear",
+        AutoFlush = "@autoFlush",
+        BacklightStrobe = "@backlightStrobe",
+        Bridge = "@bridge",
+        Brightness = "@brightness",
+        Calibrate = "@calibrate",
+        Capture = "@capture",
+        ClaimLed = "@claimLed",
+        ClipDraws = "@clipDraws",
+        ContentionPolicy = "@contentionPolicy",
+        ControlChars = "@controlChars",
+        Debounce = "@debounce",
+        DrawLog = "@drawLog",
+        DrawRle = "@drawRle",
+        DrawSprite = "@drawSprite",
+        DriveStrength = "@driveStrength",
+        DrawYieldBudget = "@drawYieldBudget",
+        DumpDrawLog = "@dumpDrawLog",
+        EdgeDetect = "@edgeDetect",
+        GlyphReplacement = "@glyphReplacement",
+        HeapAction = "@heapAction",
+        HeapThreshold = "@heapThreshold",
+        ImmediateDraw = "@immediateDraw",
+        Layer = "@layer",
+        LayerAlpha = "@layerAlpha",
+        MaxDrawsPerFlush = "@maxDrawsPerFlush",
+        MeasureText = "@measureText",
+        MirrorText = "@mirrorText",
+        NonBlockingTx = "@nonBlockingTx",
+        PixelStream = "@pixelStream",
+        PopDrawState = "@popDrawState",
+        ProgressBar = "@progressBar",
+        ProportionalText = "@proportionalText",
+        Pull = "@pull",
+        PulseTimeout = "@pulseTimeout",
+        PushDrawState = "@pushDrawState",
+        PwmFrequency = "@pwmFrequency",
+        ReadTimeout = "@readTimeout",
+        ResetRxErrors = "@resetRxErrors",
+        ResetRxHighWater = "@resetRxHighWater",
+        RunProgram = "@runProgram",
+        SendNec = "@sendNec",
+        Sleep = "@sleep",
+        Sprite = "@sprite",
+        TextBackground = "@textBackground",
+        TextRotation = "@textRotation",
+        TickFrequency = "@tickFrequency",
+        Transmit = "@transmit",
+        WriteMask = "@writeMask",
+        SlewRate = "@slewRate",
+    }
+}
+
+global_enum! {
+    /// Statistics that can be read from a building with `read <result> <building> <stat>`.
+    ///
+    /// These use negative addresses so they don't overlap with a building's regular addresses.
+    pub enum Stat(|i| -(i as f64) - 1.) {
+        SpiBytesWritten = "@spiBytesWritten",
+        SpiBytesPerSecond = "@spiBytesPerSecond",
+        ReplacedGlyphs = "@replacedGlyphs",
+        OutOfBoundsPixels = "@outOfBoundsPixels",
+        StrokeWidth = "@strokeWidth",
+        RxHighWater = "@rxHighWater",
+        PinContention = "@pinContention",
+        TextWidth = "@textWidth",
+        TextHeight = "@textHeight",
+        DeferredDraws = "@deferredDraws",
+        DisplayRefreshRate = "@displayRefreshRate",
+        CaptureX = "@captureX",
+        CaptureY = "@captureY",
+        AdcRaw = "@adcRaw",
+        HeapFree = "@heapFree",
+        FramingErrors = "@framingErrors",
+        ParityErrors = "@parityErrors",
+        DrawErrors = "@drawErrors",
+        TxHighWater = "@txHighWater",
+    }
+}
+
+global_enum! {
+    /// Why the chip last reset, read once at boot into `@resetReason`.
+    pub enum ResetReason(|i| i as f64) {
+        PowerOn = "@resetPowerOn",
+        Brownout = "@resetBrownout",
+        RunPin = "@resetRunPin",
+        Debugger = "@resetDebugger",
+        Watchdog = "@resetWatchdog",
+        SoftReset = "@resetSoft",
+    }
+}
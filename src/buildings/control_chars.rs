@@ -0,0 +1,46 @@
+use alloc::{rc::Rc, string::String};
+use core::{cell::Cell, fmt::Write};
+
+#[derive(Clone, Copy, Default)]
+enum Mode {
+    #[default]
+    Keep,
+    Strip,
+    Escape,
+}
+
+/// Shared setting for what to do with control characters (except newlines and tabs) in text
+/// sent to a terminal, so printing computed or binary data can't confuse it.
+#[derive(Clone, Default)]
+pub struct ControlChars(Rc<Cell<Mode>>);
+
+impl ControlChars {
+    /// Sets the mode from mlog: 0 keeps control characters, 1 strips them, and 2 escapes them
+    /// as `\xNN`. Other values are ignored.
+    pub fn set_mode(&self, mode: f64) {
+        self.0.set(match mode as i32 {
+            0 => Mode::Keep,
+            1 => Mode::Strip,
+            2 => Mode::Escape,
+            _ => return,
+        });
+    }
+
+    pub fn filter(&self, text: String) -> String {
+        let mode = self.0.get();
+        let is_unsafe = |c: char| c.is_control() && c != '\n' && c != '\t';
+        if matches!(mode, Mode::Keep) || !text.contains(is_unsafe) {
+            return text;
+        }
+
+        let mut result = String::with_capacity(text.len());
+        for c in text.chars() {
+            if !is_unsafe(c) {
+                result.push(c);
+            } else if let Mode::Escape = mode {
+                write!(result, "\\x{:02x}", c as u32).unwrap();
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,155 @@
+use embassy_rp::gpio::{Flex, Level, Pull};
+use embassy_time::{Duration, Instant, block_for};
+use mindy::{
+    types::LAccess,
+    vm::{CustomBuildingData, LValue, LogicVM, ProcessorState},
+};
+
+use super::{SensorGetter, Sensors};
+
+// each phase of the response is at most ~80us, so anything longer means the sensor is gone
+const LEVEL_TIMEOUT: Duration = Duration::from_micros(200);
+// a 0 bit is high for ~26us, and a 1 bit is high for ~70us
+const ONE_BIT_THRESHOLD: Duration = Duration::from_micros(48);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DhtModel {
+    Dht11,
+    Dht22,
+}
+
+impl DhtModel {
+    fn start_signal(self) -> Duration {
+        match self {
+            Self::Dht11 => Duration::from_millis(18),
+            Self::Dht22 => Duration::from_millis(1),
+        }
+    }
+
+    fn min_interval(self) -> Duration {
+        match self {
+            Self::Dht11 => Duration::from_secs(1),
+            Self::Dht22 => Duration::from_secs(2),
+        }
+    }
+
+    /// Returns (temperature in C, relative humidity in %).
+    fn decode(self, data: [u8; 4]) -> (f64, f64) {
+        let (temperature, humidity) = match self {
+            Self::Dht11 => (
+                (data[2] & 0x7f) as f64 + data[3] as f64 / 10.,
+                data[0] as f64 + data[1] as f64 / 10.,
+            ),
+            Self::Dht22 => (
+                u16::from_be_bytes([data[2] & 0x7f, data[3]]) as f64 / 10.,
+                u16::from_be_bytes([data[0], data[1]]) as f64 / 10.,
+            ),
+        };
+
+        // the high bit of the temperature is a sign bit for both models
+        if data[2] & 0x80 != 0 {
+            (-temperature, humidity)
+        } else {
+            (temperature, humidity)
+        }
+    }
+}
+
+/// DHT11/DHT22 temperature and humidity sensor.
+///
+/// Address 0 reads the temperature in C, address 1 reads the relative humidity in %. Both read
+/// null until the first successful measurement.
+pub struct DhtData<'a> {
+    pin: Flex<'a>,
+    model: DhtModel,
+    last_attempt: Option<Instant>,
+    reading: Option<(f64, f64)>,
+}
+
+impl<'a> DhtData<'a> {
+    pub fn new(mut pin: Flex<'a>, model: DhtModel) -> Self {
+        pin.set_pull(Pull::Up);
+        pin.set_as_input();
+        Self {
+            pin,
+            model,
+            last_attempt: None,
+            reading: None,
+        }
+    }
+
+    fn reading(&mut self) -> Option<(f64, f64)> {
+        if self
+            .last_attempt
+            .is_none_or(|time| time.elapsed() >= self.model.min_interval())
+        {
+            self.last_attempt = Some(Instant::now());
+            if let Some(data) = self.measure() {
+                self.reading = Some(self.model.decode(data));
+            }
+        }
+        self.reading
+    }
+
+    fn measure(&mut self) -> Option<[u8; 4]> {
+        self.pin.set_level(Level::Low);
+        self.pin.set_as_output();
+        block_for(self.model.start_signal());
+        self.pin.set_as_input();
+
+        // the bit timings are too tight to survive being interrupted
+        let bytes = cortex_m::interrupt::free(|_| {
+            // response: ~80us low, ~80us high, then the first bit starts low
+            self.wait_for_level(Level::Low)?;
+            self.wait_for_level(Level::High)?;
+            self.wait_for_level(Level::Low)?;
+
+            let mut bytes = [0u8; 5];
+            for i in 0..40 {
+                let start = self.wait_for_level(Level::High)?;
+                let end = self.wait_for_level(Level::Low)?;
+                if end - start > ONE_BIT_THRESHOLD {
+                    bytes[i / 8] |= 0x80 >> (i % 8);
+                }
+            }
+            Some(bytes)
+        })?;
+
+        let checksum = bytes[..4].iter().fold(0u8, |acc, &b| acc.wrapping_add(b));
+        (checksum == bytes[4]).then(|| [bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+
+    fn wait_for_level(&self, level: Level) -> Option<Instant> {
+        let start = Instant::now();
+        while self.pin.get_level() != level {
+            if start.elapsed() > LEVEL_TIMEOUT {
+                return None;
+            }
+        }
+        Some(Instant::now())
+    }
+}
+
+impl CustomBuildingData for DhtData<'_> {
+    fn read(&mut self, _: &mut ProcessorState, _: &LogicVM, address: LValue) -> Option<LValue> {
+        let reading = self.reading();
+        Some(match (address.num_usize(), reading) {
+            (Ok(0), Some((temperature, _))) => temperature.into(),
+            (Ok(1), Some((_, humidity))) => humidity.into(),
+            _ => LValue::NULL,
+        })
+    }
+
+    fn sensor(&mut self, _: &mut ProcessorState, _: &LogicVM, sensor: LAccess) -> Option<LValue> {
+        self.read_sensor(sensor)
+    }
+}
+
+impl Sensors for DhtData<'_> {
+    fn sensors<'s>() -> &'s [(LAccess, SensorGetter<Self>)]
+    where
+        Self: 's,
+    {
+        &[(LAccess::MemoryCapacity, |_| 2.into())]
+    }
+}
@@ -0,0 +1,1323 @@
+use alloc::{collections::VecDeque, rc::Rc, string::String, vec::Vec};
+use core::{cell::Cell, fmt::Write, mem};
+
+#[cfg(feature = "backlight_pwm")]
+use embassy_time::Duration;
+use embassy_time::Instant;
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, ascii::FONT_6X13},
+    pixelcolor::Rgb888,
+    prelude::*,
+    primitives::{Arc, Line, PrimitiveStyle, Rectangle, Sector, Triangle},
+    text::{
+        Alignment, Baseline, LineHeight, Text, TextStyle, TextStyleBuilder, renderer::TextRenderer,
+    },
+};
+use mindy::{
+    types::LAccess,
+    vm::{
+        CustomBuildingData, InstructionResult, LValue, LogicVM, ProcessorState,
+        instructions::DrawCommand,
+    },
+};
+
+#[cfg(feature = "framebuffer")]
+use super::image_bank::encode_rle;
+use super::{
+    ConfigOption, DrawLog, FramesData, ImageBankData, SensorGetter, Sensors, SerialWriter, Stat,
+    image_bank::decode_rle,
+};
+#[cfg(feature = "backlight_pwm")]
+use crate::backlight::Backlight;
+#[cfg(feature = "bounds_check")]
+use crate::bounds_check::OutOfBoundsCounter;
+#[cfg(feature = "framebuffer")]
+use crate::framebuffer::{Framebuffer, FramebufferColor};
+#[cfg(feature = "proportional_text")]
+use crate::proportional_text::ProportionalTextStyle;
+#[cfg(feature = "sprites")]
+use crate::sprites::{SPRITE_WIDTH, SPRITES};
+use crate::{counting_spi::ByteCounter, instruction_counter::InstructionCounter, rotated::Rotated};
+
+// longer strobes would just look like flicker
+#[cfg(feature = "backlight_pwm")]
+const MAX_BACKLIGHT_STROBE: Duration = Duration::from_millis(16);
+
+// maximum number of draw commands left over for the next flush by @maxDrawsPerFlush
+const MAX_DEFERRED_DRAWS: usize = 512;
+
+// maximum number of draw states saved with @pushDrawState
+const MAX_DRAW_STATE_DEPTH: usize = 16;
+
+const TEXT_LINE_HEIGHT: u32 = 13;
+
+// number of intervals between flushes to average @displayRefreshRate over
+const REFRESH_RATE_SAMPLES: usize = 6;
+
+pub const DISPLAY_RESET_COLOR: Rgb888 = Rgb888::new(0x56, 0x56, 0x66);
+
+// Mindustry's Align flags (center is the absence of the others)
+pub(super) const ALIGN_TOP: u8 = 1 << 1;
+pub(super) const ALIGN_BOTTOM: u8 = 1 << 2;
+pub(super) const ALIGN_LEFT: u8 = 1 << 3;
+pub(super) const ALIGN_RIGHT: u8 = 1 << 4;
+
+/// Number of draws that failed and were skipped, shared with draws that don't go through mlog
+/// (eg. pixel streams).
+#[derive(Clone, Default)]
+pub struct DrawErrorCounter(Rc<Cell<u64>>);
+
+impl DrawErrorCounter {
+    pub fn get(&self) -> u64 {
+        self.0.get()
+    }
+
+    pub fn record<E>(&self, result: Result<(), E>) {
+        if result.is_err() {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+}
+
+/// Affine transform set by `draw translate`, `draw scale` and `draw rotate`.
+///
+/// Each command is applied before the ones that came before it, like Mindustry's transform
+/// matrix, so eg. translations after a rotation are rotated too.
+#[derive(Clone, Copy)]
+struct Transform {
+    // [x', y'] = [[a, b], [c, d]] * [x, y] + [tx, ty]
+    a: f32,
+    b: f32,
+    c: f32,
+    d: f32,
+    tx: f32,
+    ty: f32,
+}
+
+impl Transform {
+    const IDENTITY: Self = Self {
+        a: 1.,
+        b: 0.,
+        c: 0.,
+        d: 1.,
+        tx: 0.,
+        ty: 0.,
+    };
+
+    fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            self.a * x + self.b * y + self.tx,
+            self.c * x + self.d * y + self.ty,
+        )
+    }
+
+    fn translate(&mut self, x: f32, y: f32) {
+        (self.tx, self.ty) = self.apply(x, y);
+    }
+
+    fn scale(&mut self, x: f32, y: f32) {
+        self.a *= x;
+        self.c *= x;
+        self.b *= y;
+        self.d *= y;
+    }
+
+    fn rotate(&mut self, degrees: f32) {
+        let (sin, cos) = libm::sincosf(degrees.to_radians());
+        let Self { a, b, c, d, .. } = *self;
+        self.a = a * cos + b * sin;
+        self.b = b * cos - a * sin;
+        self.c = c * cos + d * sin;
+        self.d = d * cos - c * sin;
+    }
+
+    /// Whether axis-aligned shapes stay axis-aligned.
+    fn is_axis_aligned(&self) -> bool {
+        self.b == 0. && self.c == 0.
+    }
+
+    /// Rotation of the x axis, in degrees counterclockwise.
+    fn angle(&self) -> f32 {
+        libm::atan2f(self.c, self.a).to_degrees()
+    }
+
+    /// Scales a length that doesn't have a direction (eg. a radius), by the average of the x and
+    /// y scale.
+    fn scale_length(&self, length: f32) -> f32 {
+        length * (libm::hypotf(self.a, self.c) + libm::hypotf(self.b, self.d)) / 2.
+    }
+}
+
+/// Draw state saved with `@pushDrawState` and restored with `@popDrawState`.
+struct DrawState<C: PixelColor> {
+    line_style: PrimitiveStyle<C>,
+    fill_style: PrimitiveStyle<C>,
+    char_style: MonoTextStyle<'static, C>,
+    stroke_width: u32,
+    alpha: u8,
+    transform: Transform,
+    text_rotation: u8,
+}
+
+pub struct DisplayData<T>
+where
+    T: DrawTarget,
+    T::Color: From<Rgb888>,
+{
+    display: T,
+    // sends the display's own buffer to the panel, for displays that draw into RAM
+    flush: Option<fn(&mut T) -> Result<(), T::Error>>,
+    #[cfg(feature = "framebuffer")]
+    framebuffer: Option<Framebuffer<T::Color>>,
+    // whether to copy each flushed area to the image bank, and the logic position of the last one
+    #[cfg(feature = "framebuffer")]
+    capture: bool,
+    #[cfg(feature = "framebuffer")]
+    captured_at: Point,
+    size: Size,
+    line_style: PrimitiveStyle<T::Color>,
+    fill_style: PrimitiveStyle<T::Color>,
+    char_style: MonoTextStyle<'static, T::Color>,
+    // width set by `draw stroke`, before scaling
+    stroke_width: u32,
+    // alpha set by `draw color`, only used to blend with the framebuffer
+    alpha: u8,
+    transform: Transform,
+    draw_states: Vec<DrawState<T::Color>>,
+    clip_draws: bool,
+    immediate: bool,
+    progress_bar: Option<f64>,
+    // commands left over from flushes that hit @maxDrawsPerFlush, drawn before the next ones
+    deferred: VecDeque<DrawCommand>,
+    max_draws_per_flush: usize,
+    draw_log: Option<DrawLog>,
+    frames: Option<FramesData>,
+    image_bank: Option<ImageBankData>,
+    spi_counter: Option<ByteCounter>,
+    spi_bytes_per_second: f64,
+    // times of the most recent flushes, oldest first
+    flush_times: VecDeque<Instant>,
+    #[cfg(feature = "backlight_pwm")]
+    backlight: Option<Backlight<'static>>,
+    #[cfg(feature = "backlight_pwm")]
+    backlight_strobe: Duration,
+    // when to turn the backlight back on after a strobe
+    #[cfg(feature = "backlight_pwm")]
+    unblank_at: Option<Instant>,
+    instruction_counter: Option<(InstructionCounter, f64)>,
+    draw_yield_budget: f64,
+    text_mirror: Option<SerialWriter>,
+    mirror_text: bool,
+    text_background: Option<T::Color>,
+    // quarter turns counterclockwise set by @textRotation
+    text_rotation: u8,
+    auto_clear: Option<T::Color>,
+    // whether the program started since the last draw, for auto_clear
+    restarted: bool,
+    glyph_replacement: Option<char>,
+    replaced_glyphs: u64,
+    // whether text is drawn with each glyph only as wide as its ink
+    #[cfg(feature = "proportional_text")]
+    proportional_text: bool,
+    // size of the text last measured with @measureText
+    measured_text: Size,
+    // draws that failed and were skipped
+    draw_errors: DrawErrorCounter,
+    // sprite drawn by @drawSprite
+    #[cfg(feature = "sprites")]
+    sprite: usize,
+    #[cfg(feature = "bounds_check")]
+    out_of_bounds: Option<OutOfBoundsCounter>,
+    enabled: bool,
+}
+
+/// Packs a color like Mindustry's colors, so it can be passed back to `draw col`. `None` is
+/// transparent.
+pub(super) fn pack_color(color: Option<Rgb888>) -> LValue {
+    match color {
+        Some(color) => pack_color_with_alpha(color, u8::MAX),
+        None => pack_color_with_alpha(Rgb888::BLACK, 0),
+    }
+}
+
+fn pack_color_with_alpha(color: Rgb888, alpha: u8) -> LValue {
+    f64::from_bits(u32::from_be_bytes([color.r(), color.g(), color.b(), alpha]).into()).into()
+}
+
+/// Unpacks a color packed like Mindustry's colors, or `None` if it's transparent.
+pub(super) fn unpack_color(packed: f64) -> Option<Rgb888> {
+    let [r, g, b, a] = (packed.to_bits() as u32).to_be_bytes();
+    (a > 0).then(|| Rgb888::new(r, g, b))
+}
+
+// FONT_6X13 only has printable ASCII
+fn is_supported_glyph(c: char) -> bool {
+    c == '\n' || (' '..='~').contains(&c)
+}
+
+impl<T> DisplayData<T>
+where
+    T: DrawTarget,
+    T::Color: From<Rgb888>,
+    Rgb888: From<T::Color>,
+{
+    pub fn new(mut display: T, reset: bool) -> Result<Self, T::Error> {
+        if reset {
+            display.clear(DISPLAY_RESET_COLOR.into())?;
+        }
+
+        let color = Rgb888::WHITE.into();
+        Ok(Self {
+            size: display.bounding_box().size,
+            display,
+            flush: None,
+            #[cfg(feature = "framebuffer")]
+            framebuffer: None,
+            #[cfg(feature = "framebuffer")]
+            capture: false,
+            #[cfg(feature = "framebuffer")]
+            captured_at: Point::zero(),
+            line_style: PrimitiveStyle::with_stroke(color, 1),
+            fill_style: PrimitiveStyle::with_fill(color),
+            char_style: MonoTextStyle::new(&FONT_6X13, color),
+            stroke_width: 1,
+            alpha: u8::MAX,
+            transform: Transform::IDENTITY,
+            draw_states: Vec::new(),
+            // clipping would hide the pixels that bounds_check is meant to count
+            clip_draws: !cfg!(feature = "bounds_check"),
+            immediate: false,
+            progress_bar: None,
+            deferred: VecDeque::new(),
+            max_draws_per_flush: 0,
+            draw_log: None,
+            frames: None,
+            image_bank: None,
+            spi_counter: None,
+            spi_bytes_per_second: 0.,
+            flush_times: VecDeque::with_capacity(REFRESH_RATE_SAMPLES + 1),
+            #[cfg(feature = "backlight_pwm")]
+            backlight: None,
+            #[cfg(feature = "backlight_pwm")]
+            backlight_strobe: Duration::from_ticks(0),
+            #[cfg(feature = "backlight_pwm")]
+            unblank_at: None,
+            instruction_counter: None,
+            draw_yield_budget: 0.,
+            text_mirror: None,
+            mirror_text: false,
+            text_background: None,
+            text_rotation: 0,
+            auto_clear: None,
+            restarted: true,
+            glyph_replacement: Some('?'),
+            replaced_glyphs: 0,
+            #[cfg(feature = "proportional_text")]
+            proportional_text: false,
+            measured_text: Size::zero(),
+            draw_errors: DrawErrorCounter::default(),
+            #[cfg(feature = "sprites")]
+            sprite: 0,
+            #[cfg(feature = "bounds_check")]
+            out_of_bounds: None,
+            enabled: true,
+        })
+    }
+
+    /// Allows mlog to dim the backlight with `control config display1 @brightness <0-1>`, and
+    /// strobe it after each flush with `control config display1 @backlightStrobe <ms>`.
+    #[cfg(feature = "backlight_pwm")]
+    pub fn with_backlight(mut self, backlight: Backlight<'static>) -> Self {
+        self.backlight = Some(backlight);
+        self
+    }
+
+    /// Turns the backlight back on once a strobe is over. Called from the main loop, so the strobe
+    /// doesn't block the other tasks.
+    #[cfg(feature = "backlight_pwm")]
+    pub fn update_backlight(&mut self) {
+        if let Some(backlight) = &mut self.backlight
+            && self.unblank_at.is_some_and(|at| Instant::now() >= at)
+        {
+            self.unblank_at = None;
+            backlight.unblank();
+        }
+    }
+
+    /// Allows mlog to read how many bytes have been written to the display's SPI bus with
+    /// `read result display1 @spiBytesWritten`, and the throughput of the last flush with
+    /// `@spiBytesPerSecond`.
+    pub fn with_spi_counter(mut self, counter: ByteCounter) -> Self {
+        self.spi_counter = Some(counter);
+        self
+    }
+
+    /// Returns the counter behind `@drawErrors`, so draws to the same display from outside of mlog
+    /// can be counted too.
+    pub fn draw_error_counter(&self) -> DrawErrorCounter {
+        self.draw_errors.clone()
+    }
+
+    /// Allows mlog to skip the yield after `drawflush` until the processor has used a fraction of
+    /// its per-tick instruction budget with `control config display1 @drawYieldBudget <fraction>`.
+    pub fn with_instruction_counter(mut self, counter: InstructionCounter, ipt: f64) -> Self {
+        self.instruction_counter = Some((counter, ipt));
+        self
+    }
+
+    /// Allows mlog to record the most recent draw commands with `control config display1 @drawLog 1`
+    /// and dump them to serial with `control config display1 @dumpDrawLog`.
+    pub fn with_draw_log(mut self, writer: SerialWriter) -> Self {
+        self.draw_log = Some(DrawLog::new(writer));
+        self
+    }
+
+    /// Allows mlog to draw RLE images from `image_bank` with
+    /// `control config display1 @drawRle <x> <y>`.
+    pub fn with_image_bank(mut self, image_bank: ImageBankData) -> Self {
+        self.image_bank = Some(image_bank);
+        self
+    }
+
+    /// Calls `flush` with the display after each `drawflush`, for displays that only show what was
+    /// drawn once they're flushed (eg. the SSD1306).
+    pub fn with_flush(mut self, flush: fn(&mut T) -> Result<(), T::Error>) -> Self {
+        self.flush = Some(flush);
+        self
+    }
+
+    /// Allows mlog to count frames with the `frames` building.
+    pub fn with_frame_counter(mut self, frames: FramesData) -> Self {
+        self.frames = Some(frames);
+        self
+    }
+
+    /// Renders draw commands into `pixels` instead of the display, and sends the whole frame to
+    /// the display on `drawflush`, so redrawing every frame doesn't flicker. `pixels` must have
+    /// room for the whole display.
+    #[cfg(feature = "framebuffer")]
+    pub fn with_framebuffer(mut self, pixels: &'static mut [FramebufferColor]) -> Self {
+        let mut framebuffer = Framebuffer::new(pixels, self.size);
+        let Ok(()) = framebuffer.clear(DISPLAY_RESET_COLOR.into());
+        self.framebuffer = Some(framebuffer);
+        self
+    }
+
+    /// Adds a layer over the framebuffer, which mlog can draw to with `@layer` and fade with
+    /// `@layerAlpha`. `opaque` needs one bit per pixel. Does nothing without a framebuffer.
+    #[cfg(feature = "layers")]
+    pub fn with_layer(
+        mut self,
+        pixels: &'static mut [FramebufferColor],
+        opaque: &'static mut [u32],
+    ) -> Self {
+        if let Some(framebuffer) = &mut self.framebuffer {
+            framebuffer.add_layer(pixels, opaque);
+        }
+        self
+    }
+
+    /// Allows mlog to read how many pixels were drawn outside of the display with
+    /// `read result display1 @outOfBoundsPixels`.
+    #[cfg(feature = "bounds_check")]
+    pub fn with_out_of_bounds_counter(mut self, counter: OutOfBoundsCounter) -> Self {
+        self.out_of_bounds = Some(counter);
+        self
+    }
+
+    /// Allows mlog to also write printed text to serial, prefixed with its position, with
+    /// `control config display1 @mirrorText 1`.
+    pub fn with_text_mirror(mut self, writer: SerialWriter) -> Self {
+        self.text_mirror = Some(writer);
+        self
+    }
+
+    /// Converts a point from logic coordinates (origin at the bottom left) to display coordinates.
+    ///
+    /// Points far off-screen saturate instead of overflowing, and are left for [`Self::draw`] to
+    /// clip.
+    fn point(&self, x: impl Into<i32>, y: impl Into<i32>) -> Point {
+        let (x, y) = self.transform.apply(x.into() as f32, y.into() as f32);
+        Point::new(
+            libm::roundf(x) as i32,
+            (self.size.height as i32 - 1).saturating_sub(libm::roundf(y) as i32),
+        )
+    }
+
+    fn update_stroke_width(&mut self) {
+        self.line_style.stroke_width =
+            libm::roundf(self.transform.scale_length(self.stroke_width as f32)) as u32;
+    }
+
+    /// Replaces characters that aren't in the font, since they would otherwise be drawn as
+    /// whatever the font's own fallback glyph is.
+    fn replace_unsupported_glyphs(&mut self, text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        for c in text.chars() {
+            if is_supported_glyph(c) {
+                result.push(c);
+            } else {
+                self.replaced_glyphs += 1;
+                result.extend(self.glyph_replacement);
+            }
+        }
+        result
+    }
+
+    /// Measures the size `text` would be drawn at with `draw print`.
+    fn measure_text(&self, text: &str) -> Size {
+        // replaced the same way as when printing, without counting the replacements
+        let text = text
+            .chars()
+            .filter_map(|c| {
+                if is_supported_glyph(c) {
+                    Some(c)
+                } else {
+                    self.glyph_replacement
+                }
+            })
+            .collect::<String>();
+        let text_style = TextStyleBuilder::new()
+            .line_height(LineHeight::Pixels(TEXT_LINE_HEIGHT))
+            .build();
+        #[cfg(feature = "proportional_text")]
+        if self.proportional_text {
+            let char_style = ProportionalTextStyle::new(self.char_style);
+            return Text::with_text_style(&text, Point::zero(), char_style, text_style)
+                .bounding_box()
+                .size;
+        }
+        Text::with_text_style(&text, Point::zero(), self.char_style, text_style)
+            .bounding_box()
+            .size
+    }
+
+    /// Draws `text` rotated about `anchor` by `@textRotation`, over `@textBackground` if it's set.
+    fn draw_text<S>(
+        &mut self,
+        text: &str,
+        position: Point,
+        anchor: Point,
+        char_style: S,
+        text_style: TextStyle,
+    ) -> Result<(), T::Error>
+    where
+        S: TextRenderer<Color = T::Color>,
+    {
+        let text = Text::with_text_style(text, position, char_style, text_style);
+        if let Some(color) = self.text_background {
+            let background = text
+                .bounding_box()
+                .into_styled(PrimitiveStyle::with_fill(color));
+            self.draw(&Rotated::new(&background, anchor, self.text_rotation))?;
+        }
+        self.draw(&Rotated::new(&text, anchor, self.text_rotation))
+    }
+
+    /// Copies `area` of the framebuffer to the image bank as an RLE image, so it can be sent
+    /// elsewhere or drawn again with `@drawRle`.
+    #[cfg(feature = "framebuffer")]
+    fn capture(&mut self, area: Rectangle) {
+        let (Some(framebuffer), Some(image_bank), Some(bottom_right)) =
+            (&self.framebuffer, &self.image_bank, area.bottom_right())
+        else {
+            return;
+        };
+        // images go from the bottom up, like logic coordinates
+        let pixels = (area.top_left.y..=bottom_right.y).rev().flat_map(|y| {
+            (area.top_left.x..=bottom_right.x).map(move |x| {
+                let color = framebuffer.get(Point::new(x, y));
+                pack_color(color).num()
+            })
+        });
+        encode_rle(&mut *image_bank.borrow_mut(), area.size.width, pixels);
+        self.captured_at = Point::new(
+            area.top_left.x,
+            self.size.height as i32 - 1 - bottom_right.y,
+        );
+    }
+
+    /// Average number of flushes per second over the last few flushes, or 0 if there haven't been
+    /// enough yet.
+    fn refresh_rate(&self) -> f64 {
+        match (self.flush_times.front(), self.flush_times.back()) {
+            (Some(first), Some(last)) if last > first => {
+                (self.flush_times.len() - 1) as f64 * 1_000_000.
+                    / (*last - *first).as_micros() as f64
+            }
+            _ => 0.,
+        }
+    }
+
+    /// Draws the buffered commands without flushing, so that changes to the draw state from
+    /// `control` apply after the commands that came before them.
+    ///
+    /// Deferred commands are drawn first regardless of `@maxDrawsPerFlush`, to keep the order.
+    fn draw_buffered(&mut self, state: &mut ProcessorState) -> Result<(), T::Error> {
+        self.auto_clear()?;
+        if let Some(draw_log) = &mut self.draw_log {
+            draw_log.record_commands(&state.drawbuffer);
+        }
+        while let Some(command) = self.deferred.pop_front() {
+            self.draw_command(&command)?;
+        }
+        for command in mem::take(&mut state.drawbuffer) {
+            self.draw_command(&command)?;
+        }
+        Ok(())
+    }
+
+    /// Draws buffered commands right away if `@immediateDraw` is enabled. Called from the
+    /// instruction hook, since buildings don't see `draw`.
+    pub fn draw_immediate(&mut self, state: &mut ProcessorState) {
+        if self.immediate && self.enabled && !state.drawbuffer.is_empty() {
+            let result = self.draw_buffered(state);
+            self.record_draw_error(result);
+        }
+    }
+
+    /// Draws the buffered and deferred commands for `drawflush`, and sends the frame to the
+    /// display.
+    fn draw_frame(&mut self, state: &mut ProcessorState) -> Result<(), T::Error> {
+        self.auto_clear()?;
+        // anything past the limit waits for the next flush, so one huge frame can't stall the
+        // processor
+        self.deferred.extend(mem::take(&mut state.drawbuffer));
+        let count = match self.max_draws_per_flush {
+            0 => self.deferred.len(),
+            max => max.min(self.deferred.len()),
+        };
+        for _ in 0..count {
+            let Some(command) = self.deferred.pop_front() else {
+                break;
+            };
+            self.draw_command(&command)?;
+        }
+        // a program that keeps drawing more than the limit would fill the heap, so drop the
+        // oldest commands, which belong to frames that are already out of date
+        let stale = self.deferred.len().saturating_sub(MAX_DEFERRED_DRAWS);
+        self.deferred.drain(..stale);
+        #[cfg(feature = "framebuffer")]
+        if let Some(framebuffer) = &mut self.framebuffer
+            && let Some(area) = framebuffer.flush(&mut self.display)?
+            && self.capture
+        {
+            self.capture(area);
+        }
+        if let Some(flush) = self.flush {
+            flush(&mut self.display)?;
+        }
+        Ok(())
+    }
+
+    /// Counts a draw that failed (eg. on the SPI bus) in `@drawErrors` and carries on, so a
+    /// glitch on the display doesn't stop the program.
+    fn record_draw_error(&mut self, result: Result<(), T::Error>) {
+        self.draw_errors.record(result);
+    }
+
+    fn push_draw_state(&mut self) {
+        if self.draw_states.len() < MAX_DRAW_STATE_DEPTH {
+            self.draw_states.push(DrawState {
+                line_style: self.line_style,
+                fill_style: self.fill_style,
+                char_style: self.char_style,
+                stroke_width: self.stroke_width,
+                alpha: self.alpha,
+                transform: self.transform,
+                text_rotation: self.text_rotation,
+            });
+        }
+    }
+
+    fn pop_draw_state(&mut self) {
+        if let Some(draw_state) = self.draw_states.pop() {
+            self.line_style = draw_state.line_style;
+            self.fill_style = draw_state.fill_style;
+            self.char_style = draw_state.char_style;
+            self.stroke_width = draw_state.stroke_width;
+            self.alpha = draw_state.alpha;
+            self.transform = draw_state.transform;
+            self.text_rotation = draw_state.text_rotation;
+        }
+    }
+
+    /// Draws a rect from logic coordinates, as two triangles or four lines if it's been rotated.
+    /// Rects without a positive width and height aren't drawn.
+    fn draw_rect(
+        &mut self,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        style: PrimitiveStyle<T::Color>,
+    ) -> Result<(), T::Error> {
+        if width <= 0 || height <= 0 {
+            return Ok(());
+        }
+
+        let (right, top) = (x.saturating_add(width), y.saturating_add(height));
+        let corners = [
+            self.point(x, y),
+            self.point(right, y),
+            self.point(right, top),
+            self.point(x, top),
+        ];
+
+        // the far corner is just outside of the rectangle, so step back from it by a pixel
+        // towards the near one, whichever way the transform flips it
+        if self.transform.is_axis_aligned() {
+            let [near, _, far, _] = corners;
+            let inside = Point::new(
+                near.x.saturating_sub(far.x).signum(),
+                near.y.saturating_sub(far.y).signum(),
+            );
+            let rect = Rectangle::with_corners(near, far + inside);
+            return self.draw(&rect.into_styled(style));
+        }
+
+        if style.fill_color.is_some() {
+            for [a, b, c] in [
+                [corners[0], corners[1], corners[2]],
+                [corners[0], corners[2], corners[3]],
+            ] {
+                self.draw(&Triangle::new(a, b, c).into_styled(style))?;
+            }
+        } else {
+            for i in 0..corners.len() {
+                let line = Line::new(corners[i], corners[(i + 1) % corners.len()]);
+                self.draw(&line.into_styled(style))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Draws the RLE image in the image bank with its bottom left corner at `(x, y)`, in logic
+    /// coordinates.
+    fn draw_rle(&mut self, x: i32, y: i32) -> Result<(), T::Error> {
+        let Some(image_bank) = self.image_bank.clone() else {
+            return Ok(());
+        };
+        let bank = image_bank.borrow();
+        let (width, runs) = decode_rle(&*bank);
+        self.draw_runs(x, y, width, runs)
+    }
+
+    /// Draws the sprite selected with `@sprite` from the sprite sheet in flash, the same way as
+    /// [`Self::draw_rle`]. Sprites that don't exist aren't drawn.
+    #[cfg(feature = "sprites")]
+    fn draw_sprite(&mut self, x: i32, y: i32) -> Result<(), T::Error> {
+        let Some(runs) = SPRITES.get(self.sprite) else {
+            return Ok(());
+        };
+        let runs = runs
+            .iter()
+            .map(|&(length, color)| (length.into(), f64::from_bits(color.into())));
+        self.draw_runs(x, y, SPRITE_WIDTH, runs)
+    }
+
+    /// Draws runs of `(length, packed color)` of an image `width` pixels wide, with its bottom left
+    /// corner at `(x, y)`.
+    fn draw_runs(
+        &mut self,
+        x: i32,
+        y: i32,
+        width: u32,
+        runs: impl Iterator<Item = (u32, f64)>,
+    ) -> Result<(), T::Error> {
+        if width == 0 {
+            return Ok(());
+        }
+
+        let mut n = 0;
+        for (length, color) in runs {
+            let style = unpack_color(color).map(|color| PrimitiveStyle::with_fill(color.into()));
+
+            // split the run at the end of each row
+            let end = n.saturating_add(length);
+            while n < end {
+                let (column, row) = (n % width, n / width);
+                if row > i16::MAX as u32 {
+                    return Ok(());
+                }
+                let row_length = (end - n).min(width - column);
+                if let Some(style) = style {
+                    let (x, y) = (
+                        x.saturating_add(column as i32),
+                        y.saturating_add(row as i32),
+                    );
+                    self.draw_rect(x, y, row_length as i32, 1, style)?;
+                }
+                n += row_length;
+            }
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self, color: T::Color) -> Result<(), T::Error> {
+        #[cfg(feature = "framebuffer")]
+        if let Some(framebuffer) = &mut self.framebuffer {
+            let Ok(()) = framebuffer.clear(color);
+            return Ok(());
+        }
+        self.display.clear(color)
+    }
+
+    /// Marks the program as restarted, so the display is cleared to the `@autoClear` color before
+    /// anything else is drawn. Called from the instruction hook on `end`.
+    pub fn restart(&mut self) {
+        self.restarted = true;
+    }
+
+    fn auto_clear(&mut self) -> Result<(), T::Error> {
+        if mem::take(&mut self.restarted)
+            && let Some(color) = self.auto_clear
+        {
+            self.clear(color)?;
+        }
+        Ok(())
+    }
+
+    /// Draws to the display, clipped to its bounds unless `@clipDraws` is disabled.
+    fn draw<D>(&mut self, drawable: &D) -> Result<(), T::Error>
+    where
+        D: Drawable<Color = T::Color>,
+    {
+        // the framebuffer discards pixels outside of the display by itself
+        #[cfg(feature = "framebuffer")]
+        if let Some(framebuffer) = &mut self.framebuffer {
+            let Ok(_) = if self.alpha == u8::MAX {
+                drawable.draw(framebuffer)
+            } else {
+                drawable.draw(&mut framebuffer.blended(self.alpha))
+            };
+            return Ok(());
+        }
+
+        if self.clip_draws {
+            let bounds = Rectangle::new(Point::zero(), self.size);
+            drawable.draw(&mut self.display.clipped(&bounds))?;
+        } else {
+            drawable.draw(&mut self.display)?;
+        }
+        Ok(())
+    }
+
+    fn draw_command(&mut self, command: &DrawCommand) -> Result<(), T::Error> {
+        match *command {
+            // like Mindustry, this doesn't reset the transform, so programs that translate each
+            // frame need a `draw reset`
+            DrawCommand::Clear { r, g, b } => {
+                self.clear(Rgb888::new(r, g, b).into())?;
+            }
+
+            // partial alpha can only be blended with the framebuffer, since pixels can't be read
+            // back from the display, so without one any alpha above 0 is opaque
+            DrawCommand::Color { r, g, b, a } => {
+                let color = (a > 0).then(|| Rgb888::new(r, g, b).into());
+                self.alpha = a;
+                self.line_style.stroke_color = color;
+                self.fill_style.fill_color = color;
+                self.char_style.text_color = color;
+            }
+
+            DrawCommand::Stroke { width } => {
+                self.stroke_width = width.max(0) as u32;
+                self.update_stroke_width();
+            }
+
+            DrawCommand::Line { x1, y1, x2, y2 } => {
+                let line =
+                    Line::new(self.point(x1, y1), self.point(x2, y2)).into_styled(self.line_style);
+                self.draw(&line)?;
+            }
+
+            // extension: after @progressBar, the next rect is filled up to a fraction of its width
+            // and outlined
+            DrawCommand::Rect {
+                x,
+                y,
+                width,
+                height,
+                fill,
+            } => {
+                // widened so corners past the edge of the i16 range can't overflow
+                let [x, y, width, height] = [x, y, width, height].map(i32::from);
+
+                if let Some(fraction) = self.progress_bar.take() {
+                    let filled = (width as f64 * fraction).round() as i32;
+                    if filled > 0 {
+                        self.draw_rect(x, y, filled, height, self.fill_style)?;
+                    }
+                    self.draw_rect(x, y, width, height, self.line_style)?;
+                } else {
+                    let style = if fill {
+                        self.fill_style
+                    } else {
+                        self.line_style
+                    };
+                    self.draw_rect(x, y, width, height, style)?;
+                }
+            }
+
+            // extension: polys with no sides draw an arc sweeping -sides degrees counterclockwise
+            // from rotation, or a full circle if sides is 0
+            DrawCommand::Poly {
+                x,
+                y,
+                sides,
+                radius,
+                rotation,
+                fill,
+            } if sides <= 0 => {
+                let center = self.point(x, y);
+                let diameter =
+                    libm::roundf(self.transform.scale_length(radius.max(0) as f32)) as u32 * 2;
+
+                // embedded-graphics angles are clockwise because y points down
+                let start = Angle::from_degrees(-(rotation as f32 + self.transform.angle()));
+                let sweep = Angle::from_degrees(if sides == 0 { -360. } else { sides as f32 });
+
+                if fill {
+                    let sector = Sector::with_center(center, diameter, start, sweep)
+                        .into_styled(self.fill_style);
+                    self.draw(&sector)?;
+                } else {
+                    let arc = Arc::with_center(center, diameter, start, sweep)
+                        .into_styled(self.line_style);
+                    self.draw(&arc)?;
+                }
+            }
+
+            // not enough sides to make a polygon
+            DrawCommand::Poly { sides: 1..=2, .. } => {}
+
+            // regular polygon with its first vertex at rotation degrees, like Mindustry's
+            DrawCommand::Poly {
+                x,
+                y,
+                sides,
+                radius,
+                rotation,
+                fill,
+            } => {
+                let vertices = (0..sides)
+                    .map(|i| {
+                        let angle = (360. / sides as f32 * i as f32 + rotation as f32).to_radians();
+                        self.point(
+                            libm::roundf(x as f32 + radius as f32 * libm::cosf(angle)) as i32,
+                            libm::roundf(y as f32 + radius as f32 * libm::sinf(angle)) as i32,
+                        )
+                    })
+                    .collect::<Vec<_>>();
+
+                // each edge, including the one from the last vertex back to the first
+                let edges =
+                    (0..vertices.len()).map(|i| (vertices[i], vertices[(i + 1) % vertices.len()]));
+
+                if fill {
+                    // fan out from the center, like Mindustry does
+                    let center = self.point(x, y);
+                    for (start, end) in edges {
+                        let triangle =
+                            Triangle::new(center, start, end).into_styled(self.fill_style);
+                        self.draw(&triangle)?;
+                    }
+                } else {
+                    for (start, end) in edges {
+                        let line = Line::new(start, end).into_styled(self.line_style);
+                        self.draw(&line)?;
+                    }
+                }
+            }
+
+            DrawCommand::Triangle {
+                x1,
+                y1,
+                x2,
+                y2,
+                x3,
+                y3,
+            } => {
+                let triangle =
+                    Triangle::new(self.point(x1, y1), self.point(x2, y2), self.point(x3, y3))
+                        .into_styled(self.fill_style);
+                self.draw(&triangle)?;
+            }
+
+            // we don't have Mindustry's sprites, so draw a square of the current color where the
+            // image would be, to at least keep layouts the right shape
+            DrawCommand::Image {
+                x,
+                y,
+                size,
+                rotation,
+                ..
+            } => {
+                let (sin, cos) = libm::sincosf((rotation as f32).to_radians());
+                let half = size as f32 / 2.;
+                let corner = |dx: f32, dy: f32| {
+                    self.point(
+                        libm::roundf(x as f32 + dx * cos - dy * sin) as i32,
+                        libm::roundf(y as f32 + dx * sin + dy * cos) as i32,
+                    )
+                };
+                let corners = [
+                    corner(-half, -half),
+                    corner(half, -half),
+                    corner(half, half),
+                    corner(-half, half),
+                ];
+
+                for [a, b, c] in [
+                    [corners[0], corners[1], corners[2]],
+                    [corners[0], corners[2], corners[3]],
+                ] {
+                    let triangle = Triangle::new(a, b, c).into_styled(self.fill_style);
+                    self.draw(&triangle)?;
+                }
+            }
+
+            DrawCommand::Print {
+                x,
+                y,
+                alignment,
+                ref text,
+            } => {
+                // rotated text is laid out as usual, then rotated about this
+                let anchor = self.point(x, y);
+                let mut position = anchor;
+
+                let text_style = TextStyleBuilder::new()
+                    .alignment(if alignment & ALIGN_LEFT != 0 {
+                        Alignment::Left
+                    } else if alignment & ALIGN_RIGHT != 0 {
+                        // embedded-graphics includes the anchor pixel in right-aligned text,
+                        // but Mindustry doesn't
+                        position.x += 1;
+                        Alignment::Right
+                    } else {
+                        Alignment::Center
+                    })
+                    .baseline(if alignment & ALIGN_TOP != 0 {
+                        Baseline::Top
+                    } else if alignment & ALIGN_BOTTOM != 0 {
+                        Baseline::Bottom
+                    } else {
+                        Baseline::Middle
+                    })
+                    .line_height(LineHeight::Pixels(TEXT_LINE_HEIGHT))
+                    .build();
+
+                let text = self.replace_unsupported_glyphs(&text.to_string_lossy());
+                if self.mirror_text
+                    && let Some(writer) = &mut self.text_mirror
+                {
+                    writeln!(writer, "[{x},{y}] {text}").ok();
+                }
+
+                // embedded-graphics puts the baseline of the first line at the position, but
+                // Mindustry aligns the whole block of lines, so move it up to match
+                let extra_height = text.matches('\n').count() as i32 * TEXT_LINE_HEIGHT as i32;
+                match text_style.baseline {
+                    Baseline::Bottom => position.y -= extra_height,
+                    Baseline::Middle => position.y -= extra_height / 2,
+                    _ => {}
+                }
+
+                #[cfg(feature = "proportional_text")]
+                if self.proportional_text {
+                    let char_style = ProportionalTextStyle::new(self.char_style);
+                    return self.draw_text(&text, position, anchor, char_style, text_style);
+                }
+                self.draw_text(&text, position, anchor, self.char_style, text_style)?;
+            }
+
+            DrawCommand::Translate { x, y } => {
+                self.transform.translate(x as f32, y as f32);
+            }
+
+            DrawCommand::Scale { x, y } => {
+                self.transform.scale(x as f32, y as f32);
+                self.update_stroke_width();
+            }
+
+            // rotates counterclockwise about the current origin
+            DrawCommand::Rotate { degrees } => {
+                self.transform.rotate(degrees as f32);
+            }
+
+            DrawCommand::Reset => {
+                self.transform = Transform::IDENTITY;
+                self.update_stroke_width();
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T> CustomBuildingData for DisplayData<T>
+where
+    T: DrawTarget,
+    T::Color: From<Rgb888>,
+    Rgb888: From<T::Color>,
+{
+    fn drawflush(&mut self, state: &mut ProcessorState, _: &LogicVM) -> InstructionResult {
+        if !self.enabled {
+            state.drawbuffer.clear();
+            self.deferred.clear();
+            return InstructionResult::Ok;
+        }
+
+        if let Some(draw_log) = &mut self.draw_log {
+            draw_log.record_flush(&state.drawbuffer);
+        }
+
+        #[cfg(feature = "backlight_pwm")]
+        let strobe = self.backlight_strobe > Duration::from_ticks(0);
+        #[cfg(feature = "backlight_pwm")]
+        if strobe && let Some(backlight) = &mut self.backlight {
+            backlight.blank();
+        }
+
+        let start = Instant::now();
+        if self.flush_times.len() > REFRESH_RATE_SAMPLES {
+            self.flush_times.pop_front();
+        }
+        self.flush_times.push_back(start);
+
+        let start_bytes = self.spi_counter.as_ref().map(|counter| counter.get());
+
+        let result = self.draw_frame(state);
+        self.record_draw_error(result);
+        if let Some(frames) = &self.frames {
+            frames.increment();
+        }
+
+        let micros = start.elapsed().as_micros();
+        if let (Some(counter), Some(start_bytes)) = (&self.spi_counter, start_bytes)
+            && counter.get() > start_bytes
+            && micros > 0
+        {
+            self.spi_bytes_per_second =
+                (counter.get() - start_bytes) as f64 * 1_000_000. / micros as f64;
+        }
+
+        // black frame insertion: keep the backlight off while the pixels settle, so the
+        // transition from the previous frame isn't visible
+        #[cfg(feature = "backlight_pwm")]
+        if strobe {
+            self.unblank_at = Some(Instant::now() + self.backlight_strobe);
+        }
+
+        // short frames can keep running instead of waiting for the next tick
+        match &self.instruction_counter {
+            Some((counter, ipt)) if (counter.get() as f64) < self.draw_yield_budget * ipt => {
+                InstructionResult::Ok
+            }
+            _ => InstructionResult::Yield,
+        }
+    }
+
+    fn read(&mut self, _: &mut ProcessorState, _: &LogicVM, address: LValue) -> Option<LValue> {
+        if !self.enabled {
+            return Some(LValue::NULL);
+        }
+
+        Some(match (Stat::from_lvalue(&address), &self.spi_counter) {
+            (Some(Stat::SpiBytesWritten), Some(counter)) => (counter.get() as f64).into(),
+            (Some(Stat::SpiBytesPerSecond), Some(_)) => self.spi_bytes_per_second.into(),
+            (Some(Stat::ReplacedGlyphs), _) => (self.replaced_glyphs as f64).into(),
+            (Some(Stat::StrokeWidth), _) => self.stroke_width.into(),
+            (Some(Stat::TextWidth), _) => self.measured_text.width.into(),
+            (Some(Stat::TextHeight), _) => self.measured_text.height.into(),
+            (Some(Stat::DeferredDraws), _) => self.deferred.len().into(),
+            (Some(Stat::DrawErrors), _) => (self.draw_errors.get() as f64).into(),
+            (Some(Stat::DisplayRefreshRate), _) => self.refresh_rate().into(),
+            #[cfg(feature = "framebuffer")]
+            (Some(Stat::CaptureX), _) => (self.captured_at.x as f64).into(),
+            #[cfg(feature = "framebuffer")]
+            (Some(Stat::CaptureY), _) => (self.captured_at.y as f64).into(),
+            #[cfg(feature = "bounds_check")]
+            (Some(Stat::OutOfBoundsPixels), _) => match &self.out_of_bounds {
+                Some(counter) => (counter.get() as f64).into(),
+                None => LValue::NULL,
+            },
+            // pixel y * width + x in logic coordinates, as of the last flush
+            #[cfg(feature = "framebuffer")]
+            (None, _) => match (&self.framebuffer, address.num_usize()) {
+                (Some(framebuffer), Ok(i)) => {
+                    let (x, y) = (i % self.size.width as usize, i / self.size.width as usize);
+                    let point = Point::new(x as i32, self.size.height as i32 - 1 - y as i32);
+                    framebuffer
+                        .get(point)
+                        .map_or(LValue::NULL, |color| pack_color(Some(color)))
+                }
+                _ => LValue::NULL,
+            },
+            _ => LValue::NULL,
+        })
+    }
+
+    fn control(
+        &mut self,
+        state: &mut ProcessorState,
+        _: &LogicVM,
+        control: LAccess,
+        p1: &LValue,
+        p2: &LValue,
+        p3: &LValue,
+    ) -> InstructionResult {
+        match control {
+            LAccess::Enabled => {
+                self.enabled = p1.bool();
+                return InstructionResult::Ok;
+            }
+            LAccess::Config => {}
+            _ => return InstructionResult::Ok,
+        }
+
+        match (ConfigOption::from_lvalue(p1), &mut self.draw_log) {
+            (Some(ConfigOption::DrawLog), Some(draw_log)) => draw_log.set_enabled(p2.bool()),
+            (Some(ConfigOption::DumpDrawLog), Some(draw_log)) => draw_log.dump(),
+            (Some(ConfigOption::MirrorText), _) => self.mirror_text = p2.bool(),
+            (Some(ConfigOption::ClipDraws), _) => self.clip_draws = p2.bool(),
+            // takes the text from the print buffer, like printflush
+            (Some(ConfigOption::MeasureText), _) => {
+                let text = mem::take(&mut state.printbuffer).to_string_lossy();
+                self.measured_text = self.measure_text(&text);
+            }
+            (Some(ConfigOption::ImmediateDraw), _) => self.immediate = p2.bool(),
+            #[cfg(feature = "framebuffer")]
+            (Some(ConfigOption::Capture), _) => self.capture = p2.bool(),
+            (Some(ConfigOption::PushDrawState), _) if self.enabled => {
+                let result = self.draw_buffered(state);
+                self.record_draw_error(result);
+                self.push_draw_state();
+            }
+            (Some(ConfigOption::DrawRle), _) if self.enabled => {
+                let result = self
+                    .draw_buffered(state)
+                    .and_then(|()| self.draw_rle(p2.numi(), p3.numi()));
+                self.record_draw_error(result);
+            }
+            #[cfg(feature = "sprites")]
+            (Some(ConfigOption::Sprite), _) => {
+                if let Ok(sprite) = p2.num_usize() {
+                    self.sprite = sprite;
+                }
+            }
+            #[cfg(feature = "sprites")]
+            (Some(ConfigOption::DrawSprite), _) if self.enabled => {
+                let result = self
+                    .draw_buffered(state)
+                    .and_then(|()| self.draw_sprite(p2.numi(), p3.numi()));
+                self.record_draw_error(result);
+            }
+            // applies to the next rect, so draw the ones before it first
+            (Some(ConfigOption::ProgressBar), _) if self.enabled => {
+                let result = self.draw_buffered(state);
+                self.record_draw_error(result);
+                self.progress_bar = Some(p2.num().clamp(0., 1.));
+            }
+            (Some(ConfigOption::PopDrawState), _) if self.enabled => {
+                let result = self.draw_buffered(state);
+                self.record_draw_error(result);
+                self.pop_draw_state();
+            }
+            // packed color, where a transparent color disables the background
+            (Some(ConfigOption::TextBackground), _) => {
+                self.text_background = unpack_color(p2.num()).map(Into::into);
+            }
+            // only text drawn after this changes, so draw the text before it first
+            #[cfg(feature = "proportional_text")]
+            (Some(ConfigOption::ProportionalText), _) if self.enabled => {
+                let result = self.draw_buffered(state);
+                self.record_draw_error(result);
+                self.proportional_text = p2.bool();
+            }
+            // degrees counterclockwise, rounded to a multiple of 90
+            (Some(ConfigOption::TextRotation), _) if self.enabled => {
+                let result = self.draw_buffered(state);
+                self.record_draw_error(result);
+                self.text_rotation = (libm::round(p2.num() / 90.) as i64).rem_euclid(4) as u8;
+            }
+            // later draws go to the new layer, so draw the ones before it first
+            #[cfg(feature = "layers")]
+            (Some(ConfigOption::Layer), _) if self.enabled => {
+                let result = self.draw_buffered(state);
+                self.record_draw_error(result);
+                if let (Some(framebuffer), Ok(layer)) = (&mut self.framebuffer, p2.num_usize()) {
+                    framebuffer.select_layer(layer);
+                }
+            }
+            // 0 to 255, like the alpha of `draw color`
+            #[cfg(feature = "layers")]
+            (Some(ConfigOption::LayerAlpha), _) => {
+                if let (Some(framebuffer), Ok(layer)) = (&mut self.framebuffer, p2.num_usize()) {
+                    framebuffer.set_layer_alpha(layer, p3.num().clamp(0., 255.) as u8);
+                }
+            }
+            // same as above, for the color to clear to
+            (Some(ConfigOption::AutoClear), _) => {
+                self.auto_clear = unpack_color(p2.num()).map(Into::into);
+            }
+            // 0 removes unsupported glyphs, anything else must be a character code in the font
+            (Some(ConfigOption::GlyphReplacement), _) => match p2.num() as u32 {
+                0 => self.glyph_replacement = None,
+                code => {
+                    if let Some(c) = char::from_u32(code).filter(|&c| is_supported_glyph(c)) {
+                        self.glyph_replacement = Some(c);
+                    }
+                }
+            },
+            // 0 disables the limit
+            (Some(ConfigOption::MaxDrawsPerFlush), _) => {
+                self.max_draws_per_flush = p2.num().max(0.) as usize;
+            }
+            (Some(ConfigOption::DrawYieldBudget), _) => {
+                self.draw_yield_budget = p2.num().clamp(0., 1.);
+            }
+            #[cfg(feature = "backlight_pwm")]
+            (Some(ConfigOption::Brightness), _) => {
+                if let Some(backlight) = &mut self.backlight {
+                    backlight.set_brightness(p2.num());
+                }
+            }
+            #[cfg(feature = "backlight_pwm")]
+            (Some(ConfigOption::BacklightStrobe), _) => {
+                let micros = (p2.num() * 1000.).clamp(0., MAX_BACKLIGHT_STROBE.as_micros() as f64);
+                self.backlight_strobe = Duration::from_micros(micros as u64);
+            }
+            _ => {}
+        }
+        InstructionResult::Ok
+    }
+
+    fn sensor(&mut self, _: &mut ProcessorState, _: &LogicVM, sensor: LAccess) -> Option<LValue> {
+        self.read_sensor(sensor)
+    }
+}
+
+impl<T> Sensors for DisplayData<T>
+where
+    T: DrawTarget,
+    T::Color: From<Rgb888>,
+    Rgb888: From<T::Color>,
+{
+    fn sensors<'s>() -> &'s [(LAccess, SensorGetter<Self>)]
+    where
+        Self: 's,
+    {
+        &[
+            (LAccess::Enabled, |data| data.enabled.into()),
+            (LAccess::DisplayWidth, |data| data.size.width.into()),
+            (LAccess::DisplayHeight, |data| data.size.height.into()),
+            // with the alpha from `draw color`, so it can be passed back to it unchanged
+            (LAccess::Color, |data| match data.line_style.stroke_color {
+                Some(color) => pack_color_with_alpha(color.into(), data.alpha),
+                None => pack_color(None),
+            }),
+        ]
+    }
+}
@@ -0,0 +1,164 @@
+use alloc::collections::VecDeque;
+use core::fmt::{self, Write};
+
+use mindy::vm::instructions::DrawCommand;
+
+use super::{
+    SerialWriter,
+    display::{ALIGN_BOTTOM, ALIGN_LEFT, ALIGN_RIGHT, ALIGN_TOP},
+};
+
+pub const DRAW_LOG_SIZE: usize = 256;
+
+enum DrawLogEntry {
+    Command(DrawCommand),
+    Flush,
+}
+
+/// Ring buffer of the most recent draw commands received by a display.
+///
+/// The log is dumped to serial as mlog, so it can be pasted into a processor in the game or the
+/// host simulator to replay it.
+pub struct DrawLog {
+    entries: Option<VecDeque<DrawLogEntry>>,
+    writer: SerialWriter,
+}
+
+impl DrawLog {
+    pub fn new(writer: SerialWriter) -> Self {
+        Self {
+            entries: None,
+            writer,
+        }
+    }
+
+    /// The log is disabled by default, since it uses a fair bit of heap.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        if !enabled {
+            self.entries = None;
+        } else if self.entries.is_none() {
+            self.entries = Some(VecDeque::with_capacity(DRAW_LOG_SIZE));
+        }
+    }
+
+    pub fn record_flush(&mut self, commands: &[DrawCommand]) {
+        if self.entries.is_none() {
+            return;
+        }
+
+        self.record_commands(commands);
+        self.push(DrawLogEntry::Flush);
+    }
+
+    /// Records commands that were drawn without a flush.
+    pub fn record_commands(&mut self, commands: &[DrawCommand]) {
+        if self.entries.is_none() {
+            return;
+        }
+
+        for command in commands {
+            self.push(DrawLogEntry::Command(command.clone()));
+        }
+    }
+
+    fn push(&mut self, entry: DrawLogEntry) {
+        if let Some(entries) = &mut self.entries {
+            if entries.len() == DRAW_LOG_SIZE {
+                entries.pop_front();
+            }
+            entries.push_back(entry);
+        }
+    }
+
+    pub fn dump(&mut self) {
+        for entry in self.entries.iter().flatten() {
+            match entry {
+                DrawLogEntry::Command(command) => write_mlog(&mut self.writer, command),
+                DrawLogEntry::Flush => writeln!(self.writer, "drawflush display1"),
+            }
+            .unwrap();
+        }
+    }
+}
+
+fn write_mlog(f: &mut impl Write, command: &DrawCommand) -> fmt::Result {
+    match *command {
+        DrawCommand::Clear { r, g, b } => writeln!(f, "draw clear {r} {g} {b}"),
+        DrawCommand::Color { r, g, b, a } => writeln!(f, "draw color {r} {g} {b} {a}"),
+        DrawCommand::Stroke { width } => writeln!(f, "draw stroke {width}"),
+        DrawCommand::Line { x1, y1, x2, y2 } => writeln!(f, "draw line {x1} {y1} {x2} {y2}"),
+        DrawCommand::Rect {
+            x,
+            y,
+            width,
+            height,
+            fill,
+        } => writeln!(
+            f,
+            "draw {} {x} {y} {width} {height}",
+            if fill { "rect" } else { "lineRect" }
+        ),
+        DrawCommand::Poly {
+            x,
+            y,
+            sides,
+            radius,
+            rotation,
+            fill,
+        } => writeln!(
+            f,
+            "draw {} {x} {y} {sides} {radius} {rotation}",
+            if fill { "poly" } else { "linePoly" }
+        ),
+        DrawCommand::Triangle {
+            x1,
+            y1,
+            x2,
+            y2,
+            x3,
+            y3,
+        } => writeln!(f, "draw triangle {x1} {y1} {x2} {y2} {x3} {y3}"),
+        DrawCommand::Image {
+            x,
+            y,
+            size,
+            rotation,
+            ..
+        } => writeln!(f, "draw image {x} {y} null {size} {rotation}"),
+        DrawCommand::Print {
+            x,
+            y,
+            alignment,
+            ref text,
+        } => {
+            // mlog strings have no escapes other than \n, so replace quotes to keep it parseable
+            let text = text
+                .to_string_lossy()
+                .replace('\n', "\\n")
+                .replace('"', "'");
+            writeln!(f, "print \"{text}\"")?;
+            writeln!(f, "draw print {x} {y} {}", alignment_name(alignment))
+        }
+        DrawCommand::Translate { x, y } => writeln!(f, "draw translate {x} {y}"),
+        DrawCommand::Scale { x, y } => writeln!(f, "draw scale {x} {y}"),
+        // the angle is Mindustry's p4, after x, y, p1, p2 and p3
+        DrawCommand::Rotate { degrees } => writeln!(f, "draw rotate 0 0 0 0 0 {degrees}"),
+        DrawCommand::Reset => writeln!(f, "draw reset"),
+    }
+}
+
+fn alignment_name(alignment: u8) -> &'static str {
+    let vertical = alignment & (ALIGN_TOP | ALIGN_BOTTOM);
+    let horizontal = alignment & (ALIGN_LEFT | ALIGN_RIGHT);
+    match (vertical, horizontal) {
+        (ALIGN_TOP, ALIGN_LEFT) => "@topLeft",
+        (ALIGN_TOP, ALIGN_RIGHT) => "@topRight",
+        (ALIGN_TOP, _) => "@top",
+        (ALIGN_BOTTOM, ALIGN_LEFT) => "@bottomLeft",
+        (ALIGN_BOTTOM, ALIGN_RIGHT) => "@bottomRight",
+        (ALIGN_BOTTOM, _) => "@bottom",
+        (_, ALIGN_LEFT) => "@left",
+        (_, ALIGN_RIGHT) => "@right",
+        _ => "@center",
+    }
+}
@@ -0,0 +1,56 @@
+use alloc::rc::Rc;
+use core::cell::Cell;
+
+use embassy_executor::SpawnToken;
+use embassy_rp::gpio::Input;
+use embassy_time::{Duration, Instant, with_timeout};
+
+// if a pin has no edges for this long, assume the signal is constant
+const DUTY_CYCLE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[embassy_executor::task(pool_size = 4)]
+async fn duty_cycle_task(mut pin: Input<'static>, duty_cycle: Rc<Cell<Option<f64>>>) {
+    loop {
+        duty_cycle.set(Some(match measure_duty_cycle(&mut pin).await {
+            Some(value) => value,
+            // a constant signal has no edges, so its duty cycle is just its level
+            None if pin.is_high() => 1.,
+            None => 0.,
+        }));
+    }
+}
+
+async fn measure_duty_cycle(pin: &mut Input<'_>) -> Option<f64> {
+    with_timeout(DUTY_CYCLE_TIMEOUT, pin.wait_for_rising_edge())
+        .await
+        .ok()?;
+    let rise = Instant::now();
+
+    with_timeout(DUTY_CYCLE_TIMEOUT, pin.wait_for_falling_edge())
+        .await
+        .ok()?;
+    let high = rise.elapsed();
+
+    with_timeout(DUTY_CYCLE_TIMEOUT, pin.wait_for_rising_edge())
+        .await
+        .ok()?;
+    let period = rise.elapsed();
+
+    Some(high.as_ticks() as f64 / period.as_ticks() as f64)
+}
+
+/// Input pin that measures the duty cycle (high time / period) of a PWM signal.
+///
+/// Edges are timestamped by a task, so this is only accurate for fairly slow signals.
+pub struct DutyCycleInput(Rc<Cell<Option<f64>>>);
+
+impl DutyCycleInput {
+    pub fn new(pin: Input<'static>) -> (Self, SpawnToken<impl Sized>) {
+        let duty_cycle = Rc::new(Cell::new(None));
+        (Self(duty_cycle.clone()), duty_cycle_task(pin, duty_cycle))
+    }
+
+    pub fn get(&self) -> Option<f64> {
+        self.0.get()
+    }
+}
@@ -0,0 +1,64 @@
+use mindy::{
+    types::LAccess,
+    vm::{CustomBuildingData, LValue, LogicVM, ProcessorState},
+};
+
+use super::{SensorGetter, Sensors};
+
+// XIP flash is mapped here on both the RP2040 and RP2350
+const FLASH_START: usize = 0x1000_0000;
+
+// defined in memory.x
+unsafe extern "C" {
+    safe static _flash_used_end: u8;
+    safe static _flash_end: u8;
+}
+
+/// Reports how much flash the firmware uses.
+///
+/// - Address 0: bytes of flash used by the firmware (including every embedded program).
+/// - Address 1: bytes of flash free after the firmware.
+/// - Address 2: total bytes of flash.
+/// - Address 3: size in bytes of the running program's compiled code.
+pub struct FlashData {
+    program_size: usize,
+}
+
+impl FlashData {
+    pub fn new(program_size: usize) -> Self {
+        Self { program_size }
+    }
+
+    fn total() -> usize {
+        &raw const _flash_end as usize - FLASH_START
+    }
+
+    fn used() -> usize {
+        &raw const _flash_used_end as usize - FLASH_START
+    }
+}
+
+impl CustomBuildingData for FlashData {
+    fn read(&mut self, _: &mut ProcessorState, _: &LogicVM, address: LValue) -> Option<LValue> {
+        Some(match address.num_usize() {
+            Ok(0) => Self::used().into(),
+            Ok(1) => Self::total().saturating_sub(Self::used()).into(),
+            Ok(2) => Self::total().into(),
+            Ok(3) => self.program_size.into(),
+            _ => LValue::NULL,
+        })
+    }
+
+    fn sensor(&mut self, _: &mut ProcessorState, _: &LogicVM, sensor: LAccess) -> Option<LValue> {
+        self.read_sensor(sensor)
+    }
+}
+
+impl Sensors for FlashData {
+    fn sensors<'s>() -> &'s [(LAccess, SensorGetter<Self>)]
+    where
+        Self: 's,
+    {
+        &[(LAccess::MemoryCapacity, |_| 4.into())]
+    }
+}
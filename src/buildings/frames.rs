@@ -0,0 +1,44 @@
+use alloc::rc::Rc;
+use core::cell::Cell;
+
+use mindy::{
+    types::LAccess,
+    vm::{CustomBuildingData, LValue, LogicVM, ProcessorState},
+};
+
+use super::{SensorGetter, Sensors};
+
+/// Frame counter for animations that should advance once per frame, regardless of real time.
+///
+/// Address 0 reads the number of `drawflush`es the display has drawn since boot. Flushes while the
+/// display is disabled aren't counted.
+#[derive(Clone, Default)]
+pub struct FramesData(Rc<Cell<u64>>);
+
+impl FramesData {
+    pub fn increment(&self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+
+impl CustomBuildingData for FramesData {
+    fn read(&mut self, _: &mut ProcessorState, _: &LogicVM, address: LValue) -> Option<LValue> {
+        Some(match address.num_usize() {
+            Ok(0) => (self.0.get() as f64).into(),
+            _ => LValue::NULL,
+        })
+    }
+
+    fn sensor(&mut self, _: &mut ProcessorState, _: &LogicVM, sensor: LAccess) -> Option<LValue> {
+        self.read_sensor(sensor)
+    }
+}
+
+impl Sensors for FramesData {
+    fn sensors<'s>() -> &'s [(LAccess, SensorGetter<Self>)]
+    where
+        Self: 's,
+    {
+        &[(LAccess::MemoryCapacity, |_| 1.into())]
+    }
+}
@@ -1,11 +1,158 @@
-use embassy_rp::gpio::{Flex, Pull};
+#[cfg(feature = "adc")]
+use embassy_rp::adc::{Adc, Blocking, Channel};
+use embassy_rp::{
+    gpio::{Drive, Flex, Pull, SlewRate},
+    pac,
+};
+use embassy_time::{Duration, Instant};
 use mindy::{
     types::LAccess,
     vm::{CustomBuildingData, InstructionResult, LValue, LogicVM, ProcessorState},
 };
 
+#[cfg(feature = "duty_cycle")]
+use super::DutyCycleInput;
+#[cfg(feature = "pulse_in")]
+use super::PulseInput;
+use super::{ConfigOption, SensorGetter, Sensors, Stat, pwm_output::PwmChannel};
+use crate::status_led::StatusLed;
+
+enum GpioPin<'a> {
+    Digital(Flex<'a>),
+    StatusLed(StatusLed),
+    #[cfg(feature = "duty_cycle")]
+    DutyCycle(DutyCycleInput),
+    #[cfg(feature = "pulse_in")]
+    Pulse(PulseInput),
+    #[cfg(feature = "adc")]
+    Analog(Channel<'a>),
+}
+
+// the ADC is 12-bit
+#[cfg(feature = "adc")]
+const ADC_MAX: f64 = 4095.;
+
+// reading `EDGE_ADDRESS + i` returns whether pin i had an edge since it was last read
+const EDGE_ADDRESS: usize = 32;
+// reading `PULSE_HIGH_ADDRESS + i` or `PULSE_LOW_ADDRESS + i` returns the last pulse on pin i in us
+const PULSE_HIGH_ADDRESS: usize = 64;
+const PULSE_LOW_ADDRESS: usize = 96;
+// reading or writing `LEVELS_ADDRESS` reads or drives every digital pin at once, with bit i for
+// pin i
+const LEVELS_ADDRESS: usize = 128;
+
+// long enough for an HC-SR04 echo with nothing in range
+#[cfg(feature = "pulse_in")]
+const DEFAULT_PULSE_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// Edges that are latched on a pin, set with `control config gpio @edgeDetect`.
+///
+/// IO_BANK0 latches edges on every pin in its raw interrupt registers, even when the interrupt
+/// isn't enabled, so they can be caught between reads without a task per pin. Embassy only
+/// handles pins with enabled interrupts, so it won't clear them.
+#[derive(Clone, Copy)]
+enum EdgeDetect {
+    Rising,
+    Falling,
+    Both,
+}
+
+impl EdgeDetect {
+    /// Returns whether there was a matching edge since the last call, and clears it.
+    fn take(self, pin: usize) -> bool {
+        let intr = pac::IO_BANK0.intr(pin / 8);
+        let bits = intr.read();
+        let rising = bits.edge_high(pin % 8);
+        let falling = bits.edge_low(pin % 8);
+        // only clear what was read, so an edge in between isn't lost
+        intr.write(|w| {
+            w.set_edge_high(pin % 8, rising);
+            w.set_edge_low(pin % 8, falling);
+        });
+        match self {
+            Self::Rising => rising,
+            Self::Falling => falling,
+            Self::Both => rising || falling,
+        }
+    }
+}
+
+/// Where a pin write came from, to spot writes that fight over the same pin.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Writer {
+    // address of the writing processor's state
+    processor: usize,
+    // whether the pin was written through LEVELS_ADDRESS instead of its own address
+    levels: bool,
+}
+
+impl Writer {
+    fn new(state: &ProcessorState, levels: bool) -> Self {
+        Self {
+            processor: state as *const ProcessorState as usize,
+            levels,
+        }
+    }
+}
+
+/// What to do with a write to a pin that a different writer wrote last, set with
+/// `control config gpio @contentionPolicy`. Either way, the write counts towards `@pinContention`.
+#[derive(Clone, Copy)]
+enum ContentionPolicy {
+    LastWriterWins,
+    // ignore it until the last writer releases the pin by writing null
+    Reject,
+}
+
+/// Level of a bouncy input, which only changes once the pin has read the new level for `time`.
+///
+/// This is updated when the pin is read, so the program should poll it faster than `time`.
+struct Debounce {
+    time: Duration,
+    stable: bool,
+    // when the pin first read the opposite of the stable level, if it still does
+    changed_at: Option<Instant>,
+}
+
+impl Debounce {
+    fn update(&mut self, level: bool) -> bool {
+        if level == self.stable {
+            self.changed_at = None;
+        } else {
+            let changed_at = *self.changed_at.get_or_insert_with(Instant::now);
+            if changed_at.elapsed() >= self.time {
+                self.stable = level;
+                self.changed_at = None;
+            }
+        }
+        self.stable
+    }
+}
+
 pub struct GpioData<'a> {
-    pins: [Option<Flex<'a>>; 30],
+    pins: [Option<GpioPin<'a>>; 30],
+    debounce: [Option<Debounce>; 30],
+    edge_detect: [Option<EdgeDetect>; 30],
+    // pulses longer than this read as null
+    #[cfg(feature = "pulse_in")]
+    pulse_timeout: Duration,
+    // pins driven by writes to LEVELS_ADDRESS, with bit i for pin i
+    write_mask: u32,
+    // pin outputting PWM on each channel, since pins 16 apart share one
+    pwm_owners: [Option<usize>; 16],
+    // slices owned by something else, with bit i for slice i
+    reserved_pwm_slices: u16,
+    // what last wrote each pin, until it's released by writing null
+    last_writers: [Option<Writer>; 30],
+    // number of writes to a pin that was last written by a different writer
+    contention: u64,
+    contention_policy: ContentionPolicy,
+    #[cfg(feature = "adc")]
+    adc: Option<Adc<'a, Blocking>>,
+    // raw count of the last analog read
+    #[cfg(feature = "adc")]
+    adc_raw: Option<u16>,
+    enabled: bool,
 }
 
 impl<'a> GpioData<'a> {
@@ -13,59 +160,437 @@ impl<'a> GpioData<'a> {
     where
         T: IntoIterator<Item = (usize, Flex<'a>)>,
     {
-        let mut pins = [const { None }; 30];
+        let mut data = Self {
+            pins: [const { None }; 30],
+            debounce: [const { None }; 30],
+            edge_detect: [None; 30],
+            #[cfg(feature = "pulse_in")]
+            pulse_timeout: DEFAULT_PULSE_TIMEOUT,
+            write_mask: u32::MAX,
+            pwm_owners: [None; 16],
+            reserved_pwm_slices: 0,
+            last_writers: [None; 30],
+            contention: 0,
+            contention_policy: ContentionPolicy::LastWriterWins,
+            #[cfg(feature = "adc")]
+            adc: None,
+            #[cfg(feature = "adc")]
+            adc_raw: None,
+            enabled: true,
+        };
+
+        for (i, pin) in values.into_iter() {
+            data.insert(i, GpioPin::Digital(pin));
+        }
+
+        data
+    }
+
+    /// Adds pins that read their duty cycle from 0 to 1 instead of their digital level.
+    #[cfg(feature = "duty_cycle")]
+    pub fn with_duty_cycle_pins<T>(mut self, values: T) -> Self
+    where
+        T: IntoIterator<Item = (usize, DutyCycleInput)>,
+    {
+        for (i, pin) in values.into_iter() {
+            self.insert(i, GpioPin::DutyCycle(pin));
+        }
+        self
+    }
 
+    /// Adds pins that measure the length of their pulses, which can be read at
+    /// `PULSE_HIGH_ADDRESS + i` and `PULSE_LOW_ADDRESS + i`.
+    #[cfg(feature = "pulse_in")]
+    pub fn with_pulse_pins<T>(mut self, values: T) -> Self
+    where
+        T: IntoIterator<Item = (usize, PulseInput)>,
+    {
         for (i, pin) in values.into_iter() {
-            if pins[i].is_some() {
-                panic!("duplicate pin id: {i}");
+            self.insert(i, GpioPin::Pulse(pin));
+        }
+        self
+    }
+
+    /// Adds pins that read the voltage on the pin from 0 to 1 (3.3V) with the ADC, instead of their
+    /// digital level.
+    #[cfg(feature = "adc")]
+    pub fn with_analog_pins<T>(mut self, adc: Adc<'a, Blocking>, values: T) -> Self
+    where
+        T: IntoIterator<Item = (usize, Channel<'a>)>,
+    {
+        self.adc = Some(adc);
+        for (i, channel) in values.into_iter() {
+            self.insert(i, GpioPin::Analog(channel));
+        }
+        self
+    }
+
+    /// Adds a pin that mlog can hand over to the firmware's heartbeat with
+    /// `control config gpio @claimLed 0`.
+    pub fn with_status_led(mut self, i: usize, led: StatusLed) -> Self {
+        self.insert(i, GpioPin::StatusLed(led));
+        self
+    }
+
+    /// Stops pins from outputting PWM or changing the frequency on slices that are driven by
+    /// something else (eg. the RGB LED), which would otherwise be overwritten. The pins can still
+    /// be used as digital pins.
+    pub fn with_reserved_pwm_slices<T>(mut self, slices: T) -> Self
+    where
+        T: IntoIterator<Item = usize>,
+    {
+        for slice in slices.into_iter() {
+            self.reserved_pwm_slices |= 1 << slice;
+        }
+        self
+    }
+
+    fn digital_pin(&mut self, address: &LValue) -> Option<&mut Flex<'a>> {
+        match self.pins.get_mut(address.num_usize().ok()?) {
+            Some(Some(GpioPin::Digital(pin))) => Some(pin),
+            _ => None,
+        }
+    }
+
+    /// Returns the levels of the digital pins as a bitmask, without changing outputs to inputs.
+    /// Other pins read as 0.
+    fn read_levels(&mut self) -> LValue {
+        let mut levels = 0u32;
+        for (i, pin) in self.pins.iter_mut().enumerate() {
+            if let Some(GpioPin::Digital(pin)) = pin {
+                let level = pin.is_high();
+                let level = match &mut self.debounce[i] {
+                    Some(debounce) => debounce.update(level),
+                    None => level,
+                };
+                levels |= (level as u32) << i;
+            }
+        }
+        f64::from(levels).into()
+    }
+
+    /// Records `writer` as the last writer of pin `i`, and returns whether it may write to it.
+    /// Writing null releases the pin, so the next writer doesn't count as contention.
+    fn check_writer(&mut self, i: usize, writer: Writer, release: bool) -> bool {
+        let Some(last_writer) = self.last_writers.get_mut(i) else {
+            return true;
+        };
+        if last_writer.is_some_and(|last_writer| last_writer != writer) {
+            self.contention += 1;
+            if let ContentionPolicy::Reject = self.contention_policy {
+                return false;
             }
-            pins[i] = Some(pin);
         }
+        *last_writer = (!release).then_some(writer);
+        true
+    }
+
+    /// Drives each digital pin in `@writeMask` to its bit in `levels`. Pins outside of the mask,
+    /// pins that aren't digital, and pins rejected by `@contentionPolicy` are left alone.
+    fn write_levels(&mut self, levels: u32, writer: Writer) {
+        for i in 0..self.pins.len() {
+            if self.write_mask & (1 << i) == 0
+                || !matches!(self.pins[i], Some(GpioPin::Digital(_)))
+                || !self.check_writer(i, writer, false)
+            {
+                continue;
+            }
+            let Some(GpioPin::Digital(pin)) = &mut self.pins[i] else {
+                continue;
+            };
+            let channel = PwmChannel::for_pin(i);
+            let owner = &mut self.pwm_owners[channel.index()];
+            if *owner == Some(i) {
+                *owner = None;
+                channel.stop();
+            }
+            write_digital(pin, &(levels >> i & 1 == 1).into());
+        }
+    }
+
+    /// Returns the length of the last pulse at `level` on a pin in us, or null if it was already
+    /// read, it was longer than `@pulseTimeout`, or the pin doesn't measure pulses.
+    #[cfg_attr(not(feature = "pulse_in"), allow(unused_variables))]
+    fn read_pulse(&self, i: usize, level: bool) -> LValue {
+        #[cfg(feature = "pulse_in")]
+        if let Some(Some(GpioPin::Pulse(pin))) = self.pins.get(i) {
+            return pin
+                .take(level)
+                .filter(|&pulse| pulse <= self.pulse_timeout)
+                .map_or(LValue::NULL, |pulse| (pulse.as_micros() as f64).into());
+        }
+        LValue::NULL
+    }
 
-        Self { pins }
+    fn insert(&mut self, i: usize, pin: GpioPin<'a>) {
+        if self.pins[i].is_some() {
+            panic!("duplicate pin id: {i}");
+        }
+        self.pins[i] = Some(pin);
     }
 }
 
 impl CustomBuildingData for GpioData<'_> {
     fn read(&mut self, _: &mut ProcessorState, _: &LogicVM, address: LValue) -> Option<LValue> {
-        if let Ok(i) = address.num_usize()
-            && let Some(Some(pin)) = self.pins.get_mut(i)
-        {
-            pin.set_as_input();
-            Some(bool::from(pin.get_level()).into())
-        } else {
-            Some(LValue::NULL)
+        if !self.enabled {
+            return Some(LValue::NULL);
+        }
+
+        match Stat::from_lvalue(&address) {
+            Some(Stat::PinContention) => return Some((self.contention as f64).into()),
+            #[cfg(feature = "adc")]
+            Some(Stat::AdcRaw) => {
+                return Some(self.adc_raw.map_or(LValue::NULL, |raw| (raw as f64).into()));
+            }
+            _ => {}
         }
+
+        let Ok(i) = address.num_usize() else {
+            return Some(LValue::NULL);
+        };
+        match i {
+            LEVELS_ADDRESS => return Some(self.read_levels()),
+            PULSE_LOW_ADDRESS..LEVELS_ADDRESS => {
+                return Some(self.read_pulse(i - PULSE_LOW_ADDRESS, false));
+            }
+            PULSE_HIGH_ADDRESS..PULSE_LOW_ADDRESS => {
+                return Some(self.read_pulse(i - PULSE_HIGH_ADDRESS, true));
+            }
+            EDGE_ADDRESS..PULSE_HIGH_ADDRESS => {
+                let pin = i - EDGE_ADDRESS;
+                return Some(match self.edge_detect.get(pin) {
+                    Some(Some(detect)) => detect.take(pin).into(),
+                    _ => LValue::NULL,
+                });
+            }
+            _ => {}
+        }
+        Some(match self.pins.get_mut(i) {
+            Some(Some(GpioPin::Digital(pin))) => {
+                let level = read_level(pin);
+                match &mut self.debounce[i] {
+                    Some(debounce) => debounce.update(level),
+                    None => level,
+                }
+                .into()
+            }
+            Some(Some(GpioPin::StatusLed(led))) => led
+                .with_pin(|pin| read_level(pin).into())
+                .unwrap_or(LValue::NULL),
+            #[cfg(feature = "duty_cycle")]
+            Some(Some(GpioPin::DutyCycle(pin))) => pin.get().into(),
+            #[cfg(feature = "pulse_in")]
+            Some(Some(GpioPin::Pulse(pin))) => pin.level().into(),
+            #[cfg(feature = "adc")]
+            Some(Some(GpioPin::Analog(channel))) => {
+                self.adc_raw = self
+                    .adc
+                    .as_mut()
+                    .and_then(|adc| adc.blocking_read(channel).ok());
+                self.adc_raw
+                    .map_or(LValue::NULL, |raw| (raw as f64 / ADC_MAX).into())
+            }
+            _ => LValue::NULL,
+        })
     }
 
     fn write(
         &mut self,
-        _: &mut ProcessorState,
+        state: &mut ProcessorState,
         _: &LogicVM,
         address: LValue,
         value: LValue,
     ) -> InstructionResult {
-        if let Ok(i) = address.num_usize()
-            && let Some(Some(pin)) = self.pins.get_mut(i)
-        {
-            pin.set_pull(if value == LValue::NULL {
-                Pull::None
-            } else if value.bool() {
-                Pull::Up
-            } else {
-                Pull::Down
-            });
-            pin.set_level(value.bool().into());
-            pin.set_as_output();
+        if !self.enabled {
+            return InstructionResult::Ok;
+        }
+
+        let Ok(i) = address.num_usize() else {
+            return InstructionResult::Ok;
+        };
+        if i == LEVELS_ADDRESS {
+            self.write_levels(value.num().max(0.) as u32, Writer::new(state, true));
+            return InstructionResult::Ok;
+        }
+        // conflicting writes can be spotted with @pinContention
+        if !self.check_writer(i, Writer::new(state, false), value == LValue::NULL) {
+            return InstructionResult::Ok;
+        }
+        match self.pins.get_mut(i) {
+            // fractions output PWM with that duty cycle, and anything else is a digital level
+            Some(Some(GpioPin::Digital(pin))) => {
+                let channel = PwmChannel::for_pin(i);
+                let owner = &mut self.pwm_owners[channel.index()];
+                let duty = value.num();
+                if duty > 0. && duty < 1. {
+                    if self.reserved_pwm_slices & (1 << channel.slice()) != 0 {
+                        return InstructionResult::Ok;
+                    }
+                    // the last pin written takes over the channel
+                    if let Some(other) = owner.replace(i)
+                        && other != i
+                    {
+                        PwmChannel::for_pin(other).stop();
+                    }
+                    channel.set_duty(duty);
+                } else {
+                    if *owner == Some(i) {
+                        *owner = None;
+                        channel.stop();
+                    }
+                    write_digital(pin, &value);
+                }
+            }
+            Some(Some(GpioPin::StatusLed(led))) => {
+                led.with_pin(|pin| write_digital(pin, &value));
+            }
+            _ => {}
+        }
+        InstructionResult::Ok
+    }
+
+    fn control(
+        &mut self,
+        _: &mut ProcessorState,
+        _: &LogicVM,
+        control: LAccess,
+        p1: &LValue,
+        p2: &LValue,
+        p3: &LValue,
+    ) -> InstructionResult {
+        match control {
+            LAccess::Enabled => self.enabled = p1.bool(),
+            LAccess::Config => match ConfigOption::from_lvalue(p1) {
+                Some(ConfigOption::ClaimLed) => {
+                    for pin in self.pins.iter().flatten() {
+                        if let GpioPin::StatusLed(led) = pin {
+                            led.set_claimed(p2.bool());
+                        }
+                    }
+                }
+                // these take the pin as the first value
+                Some(ConfigOption::DriveStrength) => {
+                    if let Some(pin) = self.digital_pin(p2) {
+                        pin.set_drive_strength(match p3.num() {
+                            ..=2. => Drive::_2mA,
+                            ..=4. => Drive::_4mA,
+                            ..=8. => Drive::_8mA,
+                            _ => Drive::_12mA,
+                        });
+                    }
+                }
+                // 0ms disables debouncing
+                Some(ConfigOption::Debounce) => {
+                    if let Ok(i) = p2.num_usize()
+                        && let Some(pin) = self.digital_pin(p2)
+                    {
+                        let time = Duration::from_micros((p3.num().max(0.) * 1000.) as u64);
+                        self.debounce[i] = (time > Duration::from_ticks(0)).then(|| Debounce {
+                            time,
+                            stable: read_level(pin),
+                            changed_at: None,
+                        });
+                    }
+                }
+                // 0 disables it, 1 latches rising edges, 2 falling edges, and 3 both
+                Some(ConfigOption::EdgeDetect) => {
+                    if let Ok(i) = p2.num_usize()
+                        && self.digital_pin(p2).is_some()
+                    {
+                        let detect = match p3.num() as u32 {
+                            0 => None,
+                            1 => Some(EdgeDetect::Rising),
+                            2 => Some(EdgeDetect::Falling),
+                            _ => Some(EdgeDetect::Both),
+                        };
+                        // forget edges from before it was enabled
+                        if let Some(detect) = detect {
+                            detect.take(i);
+                        }
+                        self.edge_detect[i] = detect;
+                    }
+                }
+                // 0 disables the pull, 1 pulls up, and 2 pulls down, without driving the pin
+                Some(ConfigOption::Pull) => {
+                    if let Some(pin) = self.digital_pin(p2) {
+                        pin.set_pull(match p3.num() as u32 {
+                            1 => Pull::Up,
+                            2 => Pull::Down,
+                            _ => Pull::None,
+                        });
+                        pin.set_as_input();
+                    }
+                }
+                // 0 lets the last write win, and 1 rejects writes from a different writer
+                Some(ConfigOption::ContentionPolicy) => {
+                    self.contention_policy = match p2.num() as u32 {
+                        1 => ContentionPolicy::Reject,
+                        _ => ContentionPolicy::LastWriterWins,
+                    };
+                }
+                Some(ConfigOption::WriteMask) => {
+                    self.write_mask = p2.num().max(0.) as u32;
+                }
+                #[cfg(feature = "pulse_in")]
+                Some(ConfigOption::PulseTimeout) => {
+                    self.pulse_timeout = Duration::from_micros((p2.num().max(0.) * 1000.) as u64);
+                }
+                // shared by both pins on the pin's slice
+                Some(ConfigOption::PwmFrequency) => {
+                    if let Ok(i) = p2.num_usize()
+                        && self.digital_pin(p2).is_some()
+                        && self.reserved_pwm_slices & (1 << PwmChannel::for_pin(i).slice()) == 0
+                        && p3.num() > 0.
+                    {
+                        PwmChannel::for_pin(i).set_frequency(p3.num());
+                    }
+                }
+                Some(ConfigOption::SlewRate) => {
+                    if let Some(pin) = self.digital_pin(p2) {
+                        pin.set_slew_rate(if p3.bool() {
+                            SlewRate::Fast
+                        } else {
+                            SlewRate::Slow
+                        });
+                    }
+                }
+                _ => {}
+            },
+            _ => {}
         }
         InstructionResult::Ok
     }
 
     fn sensor(&mut self, _: &mut ProcessorState, _: &LogicVM, sensor: LAccess) -> Option<LValue> {
-        Some(match sensor {
-            LAccess::MemoryCapacity => self.pins.len().into(),
-            _ => return None,
-        })
+        self.read_sensor(sensor)
+    }
+}
+
+fn read_level(pin: &mut Flex<'_>) -> bool {
+    pin.set_as_input();
+    pin.get_level().into()
+}
+
+/// Drives the pin to the value's level, or releases it back to an input for null. The pull is only
+/// changed by `@pull`.
+fn write_digital(pin: &mut Flex<'_>, value: &LValue) {
+    if *value == LValue::NULL {
+        pin.set_as_input();
+    } else {
+        pin.set_level(value.bool().into());
+        pin.set_as_output();
+    }
+}
+
+impl Sensors for GpioData<'_> {
+    fn sensors<'s>() -> &'s [(LAccess, SensorGetter<Self>)]
+    where
+        Self: 's,
+    {
+        &[
+            (LAccess::Enabled, |data| data.enabled.into()),
+            (LAccess::MemoryCapacity, |data| data.pins.len().into()),
+        ]
     }
 }
 
@@ -0,0 +1,108 @@
+use alloc::rc::Rc;
+use core::cell::{Ref, RefCell, RefMut};
+
+use mindy::{
+    types::LAccess,
+    vm::{CustomBuildingData, InstructionResult, LValue, LogicVM, ProcessorState},
+};
+
+use super::{SensorGetter, Sensors};
+
+// same as a Mindustry memory bank
+const IMAGE_BANK_SIZE: usize = 512;
+
+/// Memory bank for RLE-encoded images that `display1` can draw with `@drawRle`.
+///
+/// Address 0 is the width of the image in pixels, followed by pairs of a run length and a packed
+/// color. Pixel `n` is drawn at `(x + n % width, y + n / width)`, so rows go from the bottom up
+/// like logic coordinates. A run length of 0 ends the image early, and transparent runs are
+/// skipped.
+#[derive(Clone)]
+pub struct ImageBankData(Rc<RefCell<[f64; IMAGE_BANK_SIZE]>>);
+
+impl Default for ImageBankData {
+    fn default() -> Self {
+        Self(Rc::new(RefCell::new([0.; IMAGE_BANK_SIZE])))
+    }
+}
+
+impl ImageBankData {
+    pub(super) fn borrow(&self) -> Ref<'_, [f64; IMAGE_BANK_SIZE]> {
+        self.0.borrow()
+    }
+
+    pub(super) fn borrow_mut(&self) -> RefMut<'_, [f64; IMAGE_BANK_SIZE]> {
+        self.0.borrow_mut()
+    }
+}
+
+/// Returns the width of the image in `bank` and its runs of `(length, packed color)`.
+pub(super) fn decode_rle(bank: &[f64]) -> (u32, impl Iterator<Item = (u32, f64)> + '_) {
+    let width = bank[0].clamp(0., i16::MAX as f64) as u32;
+    let runs = bank[1..]
+        .chunks_exact(2)
+        .map(|run| (run[0].max(0.) as u32, run[1]))
+        .take_while(|&(length, _)| length > 0);
+    (width, runs)
+}
+
+/// Overwrites `bank` with an image of the given width, from the packed colors of its pixels in
+/// the order they're drawn. Pixels that don't fit in the bank are left out.
+#[cfg(feature = "framebuffer")]
+pub(super) fn encode_rle(bank: &mut [f64], width: u32, pixels: impl IntoIterator<Item = f64>) {
+    bank.fill(0.);
+    bank[0] = width as f64;
+    // index of the current run, which is empty until the first pixel
+    let mut i = 1;
+    for color in pixels {
+        if bank[i] > 0. {
+            if bank[i + 1] == color {
+                bank[i] += 1.;
+                continue;
+            }
+            i += 2;
+        }
+        if i + 1 >= bank.len() {
+            break;
+        }
+        bank[i] = 1.;
+        bank[i + 1] = color;
+    }
+}
+
+impl CustomBuildingData for ImageBankData {
+    fn read(&mut self, _: &mut ProcessorState, _: &LogicVM, address: LValue) -> Option<LValue> {
+        Some(match address.num_usize() {
+            Ok(i) if i < IMAGE_BANK_SIZE => self.0.borrow()[i].into(),
+            _ => LValue::NULL,
+        })
+    }
+
+    fn write(
+        &mut self,
+        _: &mut ProcessorState,
+        _: &LogicVM,
+        address: LValue,
+        value: LValue,
+    ) -> InstructionResult {
+        if let Ok(i) = address.num_usize()
+            && i < IMAGE_BANK_SIZE
+        {
+            self.0.borrow_mut()[i] = value.num();
+        }
+        InstructionResult::Ok
+    }
+
+    fn sensor(&mut self, _: &mut ProcessorState, _: &LogicVM, sensor: LAccess) -> Option<LValue> {
+        self.read_sensor(sensor)
+    }
+}
+
+impl Sensors for ImageBankData {
+    fn sensors<'s>() -> &'s [(LAccess, SensorGetter<Self>)]
+    where
+        Self: 's,
+    {
+        &[(LAccess::MemoryCapacity, |_| IMAGE_BANK_SIZE.into())]
+    }
+}
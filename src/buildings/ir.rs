@@ -0,0 +1,191 @@
+use embassy_rp::{
+    clocks::clk_sys_freq,
+    gpio::Level,
+    peripherals::PIO0,
+    pio::{Common, Config, Direction, Pin, ShiftConfig, ShiftDirection, StateMachine},
+};
+use fixed::traits::ToFixed;
+use mindy::{
+    types::LAccess,
+    vm::{CustomBuildingData, InstructionResult, LValue, LogicVM, ProcessorState},
+};
+
+use super::{ConfigOption, SensorGetter, Sensors};
+
+const PATTERN_SIZE: usize = 256;
+
+const CARRIER_FREQUENCY: f64 = 38_000.;
+// state machine cycles per carrier period, from the program below
+const CYCLES_PER_PERIOD: f64 = 8.;
+
+// NEC protocol timings in us
+const NEC_LEADER_MARK: u32 = 9000;
+const NEC_LEADER_SPACE: u32 = 4500;
+const NEC_BIT_MARK: u32 = 562;
+const NEC_ZERO_SPACE: u32 = 562;
+const NEC_ONE_SPACE: u32 = 1687;
+
+/// Infrared transmitter (eg. an IR LED driven through a transistor), which sends pulses modulated
+/// at 38kHz with PIO, since mlog can't time them precisely enough.
+///
+/// Addresses 0 to 255 hold a pattern of durations in us, alternating between marks (carrier on)
+/// and spaces (carrier off), starting with a mark. A duration of 0 ends the pattern early. Send it
+/// with `control config ir @transmit`, or send an NEC code directly with
+/// `control config ir @sendNec <address> <command>`.
+///
+/// Sending blocks until the last pulses have been queued, so a long pattern pauses the processor.
+pub struct IrData<'a> {
+    sm: StateMachine<'a, PIO0, 0>,
+    pattern: [u32; PATTERN_SIZE],
+}
+
+impl<'a> IrData<'a> {
+    pub fn new(
+        common: &mut Common<'a, PIO0>,
+        mut sm: StateMachine<'a, PIO0, 0>,
+        pin: Pin<'a, PIO0>,
+    ) -> Self {
+        // each FIFO word is the number of carrier periods minus 1 in the low 31 bits, with the top
+        // bit set for a mark
+        let program = pio_proc::pio_asm!(
+            ".wrap_target",
+            "start:",
+            "    pull block",
+            "    out y, 1",
+            "    out x, 31",
+            "    jmp !y space",
+            "mark:",
+            "    set pins, 1 [3]",
+            "    set pins, 0 [2]",
+            "    jmp x-- mark",
+            ".wrap",
+            "space:",
+            "    nop [3]",
+            "    nop [2]",
+            "    jmp x-- space",
+            "    jmp start",
+        );
+
+        let mut config = Config::default();
+        config.use_program(&common.load_program(&program.program), &[]);
+        config.set_set_pins(&[&pin]);
+        config.shift_out = ShiftConfig {
+            threshold: 32,
+            direction: ShiftDirection::Left,
+            auto_fill: false,
+        };
+        config.clock_divider =
+            (clk_sys_freq() as f64 / (CARRIER_FREQUENCY * CYCLES_PER_PERIOD)).to_fixed();
+
+        sm.set_config(&config);
+        sm.set_pins(Level::Low, &[&pin]);
+        sm.set_pin_dirs(Direction::Out, &[&pin]);
+        sm.set_enable(true);
+
+        Self {
+            sm,
+            pattern: [0; PATTERN_SIZE],
+        }
+    }
+
+    /// Sends alternating marks and spaces, starting with a mark, each lasting the given number of
+    /// us.
+    fn send(&mut self, durations: impl IntoIterator<Item = u32>) {
+        for (i, duration) in durations.into_iter().enumerate() {
+            let periods = (duration as f64 * CARRIER_FREQUENCY / 1_000_000.).round() as u32;
+            if periods == 0 {
+                continue;
+            }
+            let mark = if i % 2 == 0 { 1 << 31 } else { 0 };
+            let word = mark | (periods - 1).min(u32::MAX >> 1);
+            while !self.sm.tx().try_push(word) {}
+        }
+    }
+
+    fn transmit(&mut self) {
+        let pattern = self.pattern;
+        self.send(pattern.into_iter().take_while(|&duration| duration > 0));
+    }
+
+    /// Sends an NEC code. Addresses above 255 are sent as extended 16-bit addresses.
+    fn send_nec(&mut self, address: u16, command: u8) {
+        let address = match u8::try_from(address) {
+            Ok(address) => [address, !address],
+            Err(_) => address.to_le_bytes(),
+        };
+        let bytes = [address[0], address[1], command, !command];
+
+        let bits = bytes
+            .into_iter()
+            .flat_map(|byte| (0..8).map(move |i| byte >> i & 1 == 1));
+        let durations = [NEC_LEADER_MARK, NEC_LEADER_SPACE]
+            .into_iter()
+            .chain(bits.flat_map(|bit| {
+                [
+                    NEC_BIT_MARK,
+                    if bit { NEC_ONE_SPACE } else { NEC_ZERO_SPACE },
+                ]
+            }))
+            // ends the last bit's space
+            .chain([NEC_BIT_MARK]);
+        self.send(durations);
+    }
+}
+
+impl CustomBuildingData for IrData<'_> {
+    fn read(&mut self, _: &mut ProcessorState, _: &LogicVM, address: LValue) -> Option<LValue> {
+        Some(match address.num_usize() {
+            Ok(i) if i < PATTERN_SIZE => self.pattern[i].into(),
+            _ => LValue::NULL,
+        })
+    }
+
+    fn write(
+        &mut self,
+        _: &mut ProcessorState,
+        _: &LogicVM,
+        address: LValue,
+        value: LValue,
+    ) -> InstructionResult {
+        if let Ok(i) = address.num_usize()
+            && i < PATTERN_SIZE
+        {
+            self.pattern[i] = value.num().max(0.) as u32;
+        }
+        InstructionResult::Ok
+    }
+
+    fn control(
+        &mut self,
+        _: &mut ProcessorState,
+        _: &LogicVM,
+        control: LAccess,
+        p1: &LValue,
+        p2: &LValue,
+        p3: &LValue,
+    ) -> InstructionResult {
+        if control == LAccess::Config {
+            match ConfigOption::from_lvalue(p1) {
+                Some(ConfigOption::Transmit) => self.transmit(),
+                Some(ConfigOption::SendNec) => {
+                    self.send_nec(p2.num() as u16, p3.num() as u8);
+                }
+                _ => {}
+            }
+        }
+        InstructionResult::Ok
+    }
+
+    fn sensor(&mut self, _: &mut ProcessorState, _: &LogicVM, sensor: LAccess) -> Option<LValue> {
+        self.read_sensor(sensor)
+    }
+}
+
+impl Sensors for IrData<'_> {
+    fn sensors<'s>() -> &'s [(LAccess, SensorGetter<Self>)]
+    where
+        Self: 's,
+    {
+        &[(LAccess::MemoryCapacity, |_| PATTERN_SIZE.into())]
+    }
+}
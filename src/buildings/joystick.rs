@@ -0,0 +1,131 @@
+use embassy_rp::{
+    adc::{Adc, Blocking, Channel},
+    gpio::Input,
+};
+use mindy::{
+    types::LAccess,
+    vm::{CustomBuildingData, InstructionResult, LValue, LogicVM, ProcessorState},
+};
+
+use super::{ConfigOption, SensorGetter, Sensors};
+
+// the ADC is 12-bit
+const ADC_MAX: f64 = 4095.;
+
+/// Analog joystick module (eg. KY-023) with two potentiometer axes and a push button.
+///
+/// Address 0 reads the x axis and address 1 reads the y axis, both from -1 to 1 with 0 at the
+/// calibrated center. Address 2 reads true while the button is pressed.
+pub struct JoystickData<'a> {
+    adc: Adc<'a, Blocking>,
+    x: Channel<'a>,
+    y: Channel<'a>,
+    button: Input<'a>,
+    // raw readings of each axis while the stick is at rest
+    center: (f64, f64),
+}
+
+impl<'a> JoystickData<'a> {
+    /// `button` should be pulled up, and pulled low by the joystick while pressed.
+    pub fn new(adc: Adc<'a, Blocking>, x: Channel<'a>, y: Channel<'a>, button: Input<'a>) -> Self {
+        let center = ADC_MAX / 2.;
+        Self {
+            adc,
+            x,
+            y,
+            button,
+            center: (center, center),
+        }
+    }
+
+    /// Uses the current position of the stick as its center.
+    fn calibrate(&mut self) {
+        if let (Some(x), Some(y)) = (self.read_raw(Axis::X), self.read_raw(Axis::Y)) {
+            self.center = (x, y);
+        }
+    }
+
+    fn read_raw(&mut self, axis: Axis) -> Option<f64> {
+        let channel = match axis {
+            Axis::X => &mut self.x,
+            Axis::Y => &mut self.y,
+        };
+        self.adc.blocking_read(channel).ok().map(f64::from)
+    }
+
+    fn read_axis(&mut self, axis: Axis) -> LValue {
+        let Some(raw) = self.read_raw(axis) else {
+            return LValue::NULL;
+        };
+        let center = match axis {
+            Axis::X => self.center.0,
+            Axis::Y => self.center.1,
+        };
+        // scale each side separately, since the center usually isn't exactly in the middle
+        let range = if raw >= center {
+            ADC_MAX - center
+        } else {
+            center
+        };
+        if range <= 0. {
+            return 0.into();
+        }
+        ((raw - center) / range).clamp(-1., 1.).into()
+    }
+
+    fn pressed(&self) -> LValue {
+        self.button.is_low().into()
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Axis {
+    X,
+    Y,
+}
+
+impl CustomBuildingData for JoystickData<'_> {
+    fn read(&mut self, _: &mut ProcessorState, _: &LogicVM, address: LValue) -> Option<LValue> {
+        Some(match address.num_usize() {
+            Ok(0) => self.read_axis(Axis::X),
+            Ok(1) => self.read_axis(Axis::Y),
+            Ok(2) => self.pressed(),
+            _ => LValue::NULL,
+        })
+    }
+
+    fn control(
+        &mut self,
+        _: &mut ProcessorState,
+        _: &LogicVM,
+        control: LAccess,
+        p1: &LValue,
+        _: &LValue,
+        _: &LValue,
+    ) -> InstructionResult {
+        if control == LAccess::Config
+            && let Some(ConfigOption::Calibrate) = ConfigOption::from_lvalue(p1)
+        {
+            self.calibrate();
+        }
+        InstructionResult::Ok
+    }
+
+    fn sensor(&mut self, _: &mut ProcessorState, _: &LogicVM, sensor: LAccess) -> Option<LValue> {
+        self.read_sensor(sensor)
+    }
+}
+
+impl Sensors for JoystickData<'_> {
+    fn sensors<'s>() -> &'s [(LAccess, SensorGetter<Self>)]
+    where
+        Self: 's,
+    {
+        &[
+            (LAccess::ShootX, |data| data.read_axis(Axis::X)),
+            (LAccess::ShootY, |data| data.read_axis(Axis::Y)),
+            (LAccess::Shooting, |data| data.pressed()),
+            (LAccess::MemoryCapacity, |_| 3.into()),
+        ]
+    }
+}
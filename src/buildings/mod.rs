@@ -1,7 +1,80 @@
+pub use auto_flush::*;
+pub use bridge::*;
+pub use config::*;
+pub use control_chars::*;
+#[cfg(feature = "dht")]
+pub use dht::*;
+pub use display::*;
+pub use draw_log::*;
+#[cfg(feature = "duty_cycle")]
+pub use duty_cycle::*;
+pub use flash::*;
+pub use frames::*;
 pub use gpio::*;
+pub use image_bank::*;
+#[cfg(feature = "ir")]
+pub use ir::*;
+#[cfg(feature = "joystick")]
+pub use joystick::*;
+#[cfg(feature = "neopixel")]
+pub use neopixel::*;
+#[cfg(feature = "one_wire")]
+pub use one_wire::*;
+#[cfg(feature = "pulse_in")]
+pub use pulse_in::*;
+pub use read_timeout::*;
+#[cfg(feature = "retained")]
+pub use retained::*;
+#[cfg(feature = "selector")]
+pub use selector::*;
+pub use sensors::*;
 pub use serial::*;
+pub use shared::*;
+pub use task_stats::*;
+#[cfg(feature = "temp_sensor")]
+pub use temp::*;
+pub use tick::*;
 pub use uart::*;
+#[cfg(feature = "ultrasonic")]
+pub use ultrasonic::*;
 
+mod auto_flush;
+mod bridge;
+mod config;
+mod control_chars;
+#[cfg(feature = "dht")]
+mod dht;
+mod display;
+mod draw_log;
+#[cfg(feature = "duty_cycle")]
+mod duty_cycle;
+mod flash;
+mod frames;
 mod gpio;
+mod image_bank;
+#[cfg(feature = "ir")]
+mod ir;
+#[cfg(feature = "joystick")]
+mod joystick;
+#[cfg(feature = "neopixel")]
+mod neopixel;
+#[cfg(feature = "one_wire")]
+mod one_wire;
+#[cfg(feature = "pulse_in")]
+mod pulse_in;
+mod pwm_output;
+mod read_timeout;
+#[cfg(feature = "retained")]
+mod retained;
+#[cfg(feature = "selector")]
+mod selector;
+mod sensors;
 mod serial;
+mod shared;
+mod task_stats;
+#[cfg(feature = "temp_sensor")]
+mod temp;
+mod tick;
 mod uart;
+#[cfg(feature = "ultrasonic")]
+mod ultrasonic;
@@ -0,0 +1,142 @@
+use embassy_rp::{
+    clocks::clk_sys_freq,
+    gpio::Level,
+    peripherals::PIO1,
+    pio::{Config, Direction, FifoJoin, Pin, Pio, ShiftConfig, ShiftDirection, StateMachine},
+};
+use embassy_time::{Duration, Instant, block_for};
+use embedded_graphics::pixelcolor::{Rgb888, RgbColor};
+use fixed::traits::ToFixed;
+use mindy::{
+    types::LAccess,
+    vm::{CustomBuildingData, InstructionResult, LValue, LogicVM, ProcessorState},
+};
+
+use super::{
+    SensorGetter, Sensors,
+    display::{pack_color, unpack_color},
+};
+
+const BIT_FREQUENCY: f64 = 800_000.;
+// state machine cycles per bit, from the program below
+const CYCLES_PER_BIT: f64 = 10.;
+
+// each LED takes 24 bits at 800kHz
+const LED_TIME: Duration = Duration::from_micros(30);
+// the joined TX FIFO holds this many LEDs that are still being sent after the last push
+const FIFO_DEPTH: u32 = 8;
+// how long the line has to stay low for the strip to latch (newer WS2812Bs need 280us)
+const LATCH_TIME: Duration = Duration::from_micros(300);
+
+/// Strip of `N` WS2812 (NeoPixel) addressable LEDs, driven with PIO.
+///
+/// Write a packed color (eg. from `packcolor`) to address `i` to set LED `i`, and show the colors
+/// with `drawflush`. Transparent colors turn the LED off. Reads return the color that was last
+/// written, packed.
+pub struct NeopixelData<'a, const N: usize> {
+    sm: StateMachine<'a, PIO1, 0>,
+    pixels: [Rgb888; N],
+    // when the strip will have latched the last colors that were sent
+    latched_at: Instant,
+}
+
+impl<'a, const N: usize> NeopixelData<'a, N> {
+    pub fn new(mut pio: Pio<'a, PIO1>, pin: Pin<'a, PIO1>) -> Self {
+        // each bit is a high pulse, which is short for a 0 and long for a 1
+        let program = pio_proc::pio_asm!(
+            ".side_set 1",
+            ".wrap_target",
+            "bitloop:",
+            "    out x, 1 side 0 [2]",
+            "    jmp !x do_zero side 1 [1]",
+            "    jmp bitloop side 1 [4]",
+            "do_zero:",
+            "    nop side 0 [4]",
+            ".wrap",
+        );
+
+        let mut config = Config::default();
+        config.use_program(&pio.common.load_program(&program.program), &[&pin]);
+        config.fifo_join = FifoJoin::TxOnly;
+        // colors are sent as 24-bit GRB, most significant bit first
+        config.shift_out = ShiftConfig {
+            threshold: 24,
+            direction: ShiftDirection::Left,
+            auto_fill: true,
+        };
+        config.clock_divider =
+            (clk_sys_freq() as f64 / (BIT_FREQUENCY * CYCLES_PER_BIT)).to_fixed();
+
+        let mut sm = pio.sm0;
+        sm.set_config(&config);
+        sm.set_pins(Level::Low, &[&pin]);
+        sm.set_pin_dirs(Direction::Out, &[&pin]);
+        sm.set_enable(true);
+
+        Self {
+            sm,
+            pixels: [Rgb888::BLACK; N],
+            latched_at: Instant::now(),
+        }
+    }
+
+    /// Sends the colors to the strip. This blocks until they've all been queued, which takes about
+    /// 30us per LED.
+    fn show(&mut self) {
+        // anything sent before the strip latches would be appended to the last frame
+        if let Some(wait) = self.latched_at.checked_duration_since(Instant::now()) {
+            block_for(wait);
+        }
+
+        for color in self.pixels {
+            let word = u32::from_be_bytes([color.g(), color.r(), color.b(), 0]);
+            while !self.sm.tx().try_push(word) {}
+        }
+
+        self.latched_at = Instant::now() + LED_TIME * FIFO_DEPTH + LATCH_TIME;
+    }
+}
+
+impl<const N: usize> CustomBuildingData for NeopixelData<'_, N> {
+    fn read(&mut self, _: &mut ProcessorState, _: &LogicVM, address: LValue) -> Option<LValue> {
+        Some(match address.num_usize() {
+            Ok(i) if i < N => pack_color(Some(self.pixels[i])),
+            _ => LValue::NULL,
+        })
+    }
+
+    fn write(
+        &mut self,
+        _: &mut ProcessorState,
+        _: &LogicVM,
+        address: LValue,
+        value: LValue,
+    ) -> InstructionResult {
+        if let Ok(i) = address.num_usize()
+            && i < N
+        {
+            self.pixels[i] = unpack_color(value.num()).unwrap_or(Rgb888::BLACK);
+        }
+        InstructionResult::Ok
+    }
+
+    fn drawflush(&mut self, state: &mut ProcessorState, _: &LogicVM) -> InstructionResult {
+        // draw commands don't mean anything to the strip
+        state.drawbuffer.clear();
+        self.show();
+        InstructionResult::Ok
+    }
+
+    fn sensor(&mut self, _: &mut ProcessorState, _: &LogicVM, sensor: LAccess) -> Option<LValue> {
+        self.read_sensor(sensor)
+    }
+}
+
+impl<const N: usize> Sensors for NeopixelData<'_, N> {
+    fn sensors<'s>() -> &'s [(LAccess, SensorGetter<Self>)]
+    where
+        Self: 's,
+    {
+        &[(LAccess::MemoryCapacity, |_| N.into())]
+    }
+}
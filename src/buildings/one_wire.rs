@@ -0,0 +1,206 @@
+use alloc::vec::Vec;
+
+use embassy_rp::gpio::{Flex, Level, Pull};
+use embassy_time::{Duration, block_for};
+use mindy::{
+    types::LAccess,
+    vm::{CustomBuildingData, InstructionResult, LValue, LogicVM, ProcessorState},
+};
+
+use super::{SensorGetter, Sensors};
+
+const SEARCH_ROM: u8 = 0xf0;
+
+// addresses of the ROM codes found by the last search
+const ROM_ADDRESS_START: usize = 8;
+const MAX_DEVICES: usize = 8;
+
+/// 1-Wire bus master (standard speed), for sensors like the DS18B20.
+///
+/// - Address 0: read or write a byte.
+/// - Address 1: read to send a reset pulse. Returns true if any device responded.
+/// - Address 2: read to search the bus for devices. Returns the number of devices found.
+/// - Address `8 + 8*i + j`: byte `j` of the ROM code of the `i`th device found by the last search.
+///
+/// The bus needs an external pull-up resistor (usually 4.7k) for reliable communication.
+pub struct OneWireData<'a> {
+    pin: Flex<'a>,
+    roms: Vec<[u8; 8]>,
+}
+
+impl<'a> OneWireData<'a> {
+    pub fn new(mut pin: Flex<'a>) -> Self {
+        pin.set_pull(Pull::Up);
+        pin.set_level(Level::Low);
+        pin.set_as_input();
+        Self {
+            pin,
+            roms: Vec::new(),
+        }
+    }
+
+    // the bus is open drain, so we only ever drive it low
+    fn drive_low(&mut self) {
+        self.pin.set_as_output();
+    }
+
+    fn release(&mut self) {
+        self.pin.set_as_input();
+    }
+
+    fn reset(&mut self) -> bool {
+        self.drive_low();
+        block_for(Duration::from_micros(480));
+
+        let presence = cortex_m::interrupt::free(|_| {
+            self.release();
+            block_for(Duration::from_micros(70));
+            self.pin.is_low()
+        });
+
+        block_for(Duration::from_micros(410));
+        presence
+    }
+
+    // the bit timings are too tight to survive being interrupted
+    fn write_bit(&mut self, bit: bool) {
+        cortex_m::interrupt::free(|_| {
+            self.drive_low();
+            if bit {
+                block_for(Duration::from_micros(6));
+                self.release();
+                block_for(Duration::from_micros(64));
+            } else {
+                block_for(Duration::from_micros(60));
+                self.release();
+                block_for(Duration::from_micros(10));
+            }
+        });
+    }
+
+    fn read_bit(&mut self) -> bool {
+        cortex_m::interrupt::free(|_| {
+            self.drive_low();
+            block_for(Duration::from_micros(6));
+            self.release();
+            block_for(Duration::from_micros(9));
+            let bit = self.pin.is_high();
+            block_for(Duration::from_micros(55));
+            bit
+        })
+    }
+
+    fn write_byte(&mut self, byte: u8) {
+        for i in 0..8 {
+            self.write_bit(byte & (1 << i) != 0);
+        }
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        (0..8).fold(0, |byte, i| byte | ((self.read_bit() as u8) << i))
+    }
+
+    // https://www.analog.com/en/resources/app-notes/1wire-search-algorithm.html
+    fn search(&mut self) -> usize {
+        self.roms.clear();
+
+        let mut rom = [0u8; 8];
+        let mut last_discrepancy = None;
+
+        while self.roms.len() < MAX_DEVICES {
+            if !self.reset() {
+                break;
+            }
+            self.write_byte(SEARCH_ROM);
+
+            let mut discrepancy = None;
+            for i in 0..64 {
+                let (byte, mask) = (i / 8, 1 << (i % 8));
+
+                let bit = self.read_bit();
+                let complement = self.read_bit();
+
+                let direction = match (bit, complement) {
+                    // no devices responded
+                    (true, true) => return self.roms.len(),
+                    // all remaining devices have the same bit here
+                    (bit, complement) if bit != complement => bit,
+                    // devices disagree, so take the 1 branch if we've already been down the 0 one
+                    _ => {
+                        let direction = match last_discrepancy {
+                            Some(last) if i < last => rom[byte] & mask != 0,
+                            Some(last) => i == last,
+                            None => false,
+                        };
+                        if !direction {
+                            discrepancy = Some(i);
+                        }
+                        direction
+                    }
+                };
+
+                if direction {
+                    rom[byte] |= mask;
+                } else {
+                    rom[byte] &= !mask;
+                }
+                self.write_bit(direction);
+            }
+
+            self.roms.push(rom);
+
+            last_discrepancy = discrepancy;
+            if last_discrepancy.is_none() {
+                break;
+            }
+        }
+
+        self.roms.len()
+    }
+}
+
+impl CustomBuildingData for OneWireData<'_> {
+    fn read(&mut self, _: &mut ProcessorState, _: &LogicVM, address: LValue) -> Option<LValue> {
+        Some(match address.num_usize() {
+            Ok(0) => self.read_byte().into(),
+            Ok(1) => self.reset().into(),
+            Ok(2) => self.search().into(),
+            Ok(i) if i >= ROM_ADDRESS_START => {
+                let i = i - ROM_ADDRESS_START;
+                match self.roms.get(i / 8) {
+                    Some(rom) => rom[i % 8].into(),
+                    None => LValue::NULL,
+                }
+            }
+            _ => LValue::NULL,
+        })
+    }
+
+    fn write(
+        &mut self,
+        _: &mut ProcessorState,
+        _: &LogicVM,
+        address: LValue,
+        value: LValue,
+    ) -> InstructionResult {
+        if let Ok(0) = address.num_usize() {
+            self.write_byte(value.numi() as u8);
+        }
+        InstructionResult::Ok
+    }
+
+    fn sensor(&mut self, _: &mut ProcessorState, _: &LogicVM, sensor: LAccess) -> Option<LValue> {
+        self.read_sensor(sensor)
+    }
+}
+
+impl Sensors for OneWireData<'_> {
+    fn sensors<'s>() -> &'s [(LAccess, SensorGetter<Self>)]
+    where
+        Self: 's,
+    {
+        &[(LAccess::MemoryCapacity, |_| {
+            (ROM_ADDRESS_START + MAX_DEVICES * 8).into()
+        })]
+    }
+}
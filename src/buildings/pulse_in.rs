@@ -0,0 +1,120 @@
+use alloc::rc::Rc;
+use core::cell::Cell;
+
+use embassy_executor::SpawnToken;
+use embassy_rp::{
+    clocks::clk_sys_freq,
+    gpio::Pull,
+    pac,
+    peripherals::PIO0,
+    pio::{Common, Config, Direction, FifoJoin, Pin, StateMachine},
+};
+use embassy_time::Duration;
+use fixed::traits::ToFixed;
+
+// state machine cycles per us, from the program below
+const CYCLES_PER_US: f64 = 2.;
+
+// FIFO words for low pulses have the top bit set
+const LOW_PULSE_BIT: u32 = 1 << 31;
+
+struct Pulses {
+    // lengths of the last high and low pulses that haven't been read yet
+    high: Cell<Option<Duration>>,
+    low: Cell<Option<Duration>>,
+}
+
+#[embassy_executor::task]
+async fn pulse_task(mut sm: StateMachine<'static, PIO0, 1>, pulses: Rc<Pulses>) {
+    loop {
+        let word = sm.rx().wait_pull().await;
+        let (pulse, micros) = if word & LOW_PULSE_BIT != 0 {
+            (&pulses.low, !word)
+        } else {
+            (&pulses.high, word)
+        };
+        pulse.set(Some(Duration::from_micros(micros.into())));
+    }
+}
+
+/// Input pin that measures the length of pulses like Arduino's `pulseIn`, without pausing the
+/// processor.
+///
+/// Pulses are timed by PIO to within a us, so they're accurate however long the processor runs
+/// before the task reads them. The FIFO holds 8 pulses, and later ones are dropped until the task
+/// catches up.
+pub struct PulseInput {
+    pin: usize,
+    pulses: Rc<Pulses>,
+}
+
+impl PulseInput {
+    pub fn new(
+        common: &mut Common<'static, PIO0>,
+        mut sm: StateMachine<'static, PIO0, 1>,
+        mut pin: Pin<'static, PIO0>,
+    ) -> (Self, SpawnToken<impl Sized>) {
+        // counts down x once per 2 cycles while the pin stays at a level, then pushes the count,
+        // inverted for low pulses so that their top bit is set. a pulse that's already started
+        // would be measured too short, so it starts from the first rising edge
+        let program = pio_proc::pio_asm!(
+            "    wait 0 pin 0",
+            "    wait 1 pin 0",
+            ".wrap_target",
+            "    mov x, ~null",
+            "high:",
+            "    jmp x-- high_test",
+            "high_test:",
+            "    jmp pin high",
+            "    mov isr, ~x",
+            "    push noblock",
+            "    mov x, ~null",
+            "low:",
+            "    jmp pin low_end",
+            "    jmp x-- low",
+            "low_end:",
+            "    mov isr, x",
+            "    push noblock",
+            ".wrap",
+        );
+
+        pin.set_pull(Pull::None);
+
+        let mut config = Config::default();
+        config.use_program(&common.load_program(&program.program), &[]);
+        config.set_in_pins(&[&pin]);
+        config.set_jmp_pin(&pin);
+        config.fifo_join = FifoJoin::RxOnly;
+        config.clock_divider = (clk_sys_freq() as f64 / (CYCLES_PER_US * 1_000_000.)).to_fixed();
+
+        sm.set_config(&config);
+        sm.set_pin_dirs(Direction::In, &[&pin]);
+        sm.set_enable(true);
+
+        let pulses = Rc::new(Pulses {
+            high: Cell::new(None),
+            low: Cell::new(None),
+        });
+        (
+            Self {
+                pin: pin.pin().into(),
+                pulses: pulses.clone(),
+            },
+            pulse_task(sm, pulses),
+        )
+    }
+
+    pub fn level(&self) -> bool {
+        // the pin belongs to PIO, so read it from the pad
+        pac::IO_BANK0.gpio(self.pin).status().read().infrompad()
+    }
+
+    /// Returns the length of the last pulse at `level` if it hasn't been returned yet.
+    pub fn take(&self, level: bool) -> Option<Duration> {
+        if level {
+            self.pulses.high.take()
+        } else {
+            self.pulses.low.take()
+        }
+    }
+}
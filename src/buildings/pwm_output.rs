@@ -0,0 +1,94 @@
+use embassy_rp::{clocks::clk_sys_freq, pac};
+
+// IO_BANK0 function numbers, the same on the RP2040 and RP2350
+const FUNCSEL_PWM: u8 = 4;
+const FUNCSEL_SIO: u8 = 5;
+
+const DEFAULT_FREQUENCY: f64 = 1000.;
+
+/// PWM slice channel that a GPIO pin outputs.
+///
+/// The GPIO pins are owned by `Flex`es, so the PWM registers are used directly instead of through
+/// `embassy_rp::pwm`, which would need to own the pin and the slice.
+#[derive(Clone, Copy)]
+pub(super) struct PwmChannel {
+    pin: usize,
+    slice: usize,
+    b: bool,
+}
+
+impl PwmChannel {
+    /// Pins `n` and `n + 16` share a channel, and pins `2n` and `2n + 1` share a slice.
+    pub fn for_pin(pin: usize) -> Self {
+        Self {
+            pin,
+            slice: (pin / 2) % 8,
+            b: pin % 2 == 1,
+        }
+    }
+
+    pub fn slice(self) -> usize {
+        self.slice
+    }
+
+    /// Index of the channel for tables of all 16 channels.
+    pub fn index(self) -> usize {
+        self.slice * 2 + self.b as usize
+    }
+
+    /// Routes the pin to its channel, and sets the channel's duty cycle from 0 to 1.
+    pub fn set_duty(self, duty: f64) {
+        let regs = pac::PWM.ch(self.slice);
+        self.start_slice();
+        let top = regs.top().read().top() as f64;
+        let level = (duty.clamp(0., 1.) * (top + 1.)).min(u16::MAX as f64) as u16;
+        regs.cc().modify(|w| {
+            if self.b {
+                w.set_b(level);
+            } else {
+                w.set_a(level);
+            }
+        });
+        pac::IO_BANK0
+            .gpio(self.pin)
+            .ctrl()
+            .write(|w| w.set_funcsel(FUNCSEL_PWM));
+    }
+
+    /// Sets the frequency in Hz of the pin's slice, which also changes it for the other pin on the
+    /// same slice.
+    pub fn set_frequency(self, frequency: f64) {
+        self.start_slice();
+        self.write_divider(frequency);
+    }
+
+    /// Gives the pin back to its `Flex`.
+    pub fn stop(self) {
+        pac::IO_BANK0
+            .gpio(self.pin)
+            .ctrl()
+            .write(|w| w.set_funcsel(FUNCSEL_SIO));
+    }
+
+    fn start_slice(self) {
+        let regs = pac::PWM.ch(self.slice);
+        if !regs.csr().read().en() {
+            regs.top().write(|w| w.set_top(u16::MAX));
+            regs.cc().write(|_| {});
+            self.write_divider(DEFAULT_FREQUENCY);
+            regs.csr().modify(|w| w.set_en(true));
+        }
+    }
+
+    fn write_divider(self, frequency: f64) {
+        let regs = pac::PWM.ch(self.slice);
+        let top = regs.top().read().top() as f64;
+        // 8.4 fixed point, so it's limited to 1 to just under 256
+        let divider =
+            (clk_sys_freq() as f64 / (frequency * (top + 1.))).clamp(1., 255. + 15. / 16.);
+        regs.div().write(|w| {
+            w.set_int(divider as u8);
+            w.set_frac(((divider % 1.) * 16.) as u8);
+        });
+    }
+}
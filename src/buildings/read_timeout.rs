@@ -0,0 +1,44 @@
+use embassy_time::{Duration, Instant};
+use mindy::vm::LValue;
+
+/// Returned by a read that timed out, since it can't be mistaken for a byte.
+pub const READ_TIMED_OUT: f64 = -1.;
+
+/// Optional deadline for reads from a building's receive buffer, so mlog doesn't have to count
+/// loop iterations to implement its own timeout.
+///
+/// While waiting, reads return null as usual. Once the timeout has passed since the first empty
+/// read, the next read returns [`READ_TIMED_OUT`] and the deadline is reset.
+#[derive(Default)]
+pub struct ReadTimeout {
+    timeout: Option<Duration>,
+    deadline: Option<Instant>,
+}
+
+impl ReadTimeout {
+    pub fn set_timeout_ms(&mut self, ms: f64) {
+        self.timeout = (ms > 0.).then(|| Duration::from_micros((ms * 1000.) as u64));
+        self.deadline = None;
+    }
+
+    pub fn poll(&mut self, value: Option<LValue>) -> LValue {
+        let Some(timeout) = self.timeout else {
+            return value.unwrap_or(LValue::NULL);
+        };
+
+        if let Some(value) = value {
+            self.deadline = None;
+            return value;
+        }
+
+        let deadline = *self
+            .deadline
+            .get_or_insert_with(|| Instant::now() + timeout);
+        if Instant::now() >= deadline {
+            self.deadline = None;
+            READ_TIMED_OUT.into()
+        } else {
+            LValue::NULL
+        }
+    }
+}
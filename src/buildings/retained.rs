@@ -0,0 +1,111 @@
+use core::{mem::MaybeUninit, ptr};
+
+use mindy::{
+    types::LAccess,
+    vm::{CustomBuildingData, InstructionResult, LValue, LogicVM, ProcessorState},
+};
+
+use super::{SensorGetter, Sensors};
+
+const RETAINED_SIZE: usize = 16;
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct Retained {
+    values: [u64; RETAINED_SIZE],
+    crc: u32,
+}
+
+// cortex-m-rt doesn't initialize .uninit at startup, so this survives soft resets
+#[unsafe(link_section = ".uninit.retained")]
+static mut RETAINED: MaybeUninit<Retained> = MaybeUninit::uninit();
+
+// CRC-32 (IEEE), to detect garbage left in RAM after a power cycle
+fn crc32(values: &[u64]) -> u32 {
+    let mut crc = !0u32;
+    for byte in values.iter().flat_map(|value| value.to_le_bytes()) {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Numbers that are kept across soft resets (eg. `@runProgram` or a panic), but not power
+/// cycles.
+///
+/// Each address from 0 to 15 stores one number. All of them read as 0 after a power cycle, or if
+/// the retained RAM was corrupted.
+pub struct RetainedData {
+    values: [u64; RETAINED_SIZE],
+}
+
+impl RetainedData {
+    /// Loads the values from the last boot. Must only be called once, since every instance
+    /// writes to the same memory.
+    pub fn load() -> Self {
+        // SAFETY: every bit pattern is a valid Retained, and only one instance exists
+        let retained = unsafe { ptr::read_volatile((&raw const RETAINED).cast::<Retained>()) };
+        let mut data = Self {
+            values: [0; RETAINED_SIZE],
+        };
+        if crc32(&retained.values) == retained.crc {
+            data.values = retained.values;
+        } else {
+            data.store();
+        }
+        data
+    }
+
+    fn store(&mut self) {
+        let retained = Retained {
+            values: self.values,
+            crc: crc32(&self.values),
+        };
+        // SAFETY: only one instance exists
+        unsafe { ptr::write_volatile((&raw mut RETAINED).cast::<Retained>(), retained) };
+    }
+}
+
+impl CustomBuildingData for RetainedData {
+    fn read(&mut self, _: &mut ProcessorState, _: &LogicVM, address: LValue) -> Option<LValue> {
+        Some(match address.num_usize() {
+            Ok(i) if i < RETAINED_SIZE => f64::from_bits(self.values[i]).into(),
+            _ => LValue::NULL,
+        })
+    }
+
+    fn write(
+        &mut self,
+        _: &mut ProcessorState,
+        _: &LogicVM,
+        address: LValue,
+        value: LValue,
+    ) -> InstructionResult {
+        if let Ok(i) = address.num_usize()
+            && i < RETAINED_SIZE
+        {
+            self.values[i] = value.num().to_bits();
+            self.store();
+        }
+        InstructionResult::Ok
+    }
+
+    fn sensor(&mut self, _: &mut ProcessorState, _: &LogicVM, sensor: LAccess) -> Option<LValue> {
+        self.read_sensor(sensor)
+    }
+}
+
+impl Sensors for RetainedData {
+    fn sensors<'s>() -> &'s [(LAccess, SensorGetter<Self>)]
+    where
+        Self: 's,
+    {
+        &[(LAccess::MemoryCapacity, |_| RETAINED_SIZE.into())]
+    }
+}
@@ -0,0 +1,59 @@
+use embassy_rp::gpio::Input;
+use mindy::{
+    types::LAccess,
+    vm::{CustomBuildingData, LValue, LogicVM, ProcessorState},
+};
+
+use super::{SensorGetter, Sensors};
+
+/// Rotary selector switch with a pin for each position, which the switch connects to ground while
+/// that position is selected.
+///
+/// Address 0 reads the selected position, starting from 0 for the first pin. It reads null unless
+/// exactly one position is active, eg. while the switch is between positions (for break-before-make
+/// switches) or if it's wired wrong.
+pub struct SelectorData<'a, const N: usize> {
+    pins: [Input<'a>; N],
+}
+
+impl<'a, const N: usize> SelectorData<'a, N> {
+    /// The pins should be pulled up.
+    pub fn new(pins: [Input<'a>; N]) -> Self {
+        Self { pins }
+    }
+
+    fn position(&self) -> LValue {
+        let mut active = self
+            .pins
+            .iter()
+            .enumerate()
+            .filter(|(_, pin)| pin.is_low())
+            .map(|(i, _)| i);
+        match (active.next(), active.next()) {
+            (Some(i), None) => i.into(),
+            _ => LValue::NULL,
+        }
+    }
+}
+
+impl<const N: usize> CustomBuildingData for SelectorData<'_, N> {
+    fn read(&mut self, _: &mut ProcessorState, _: &LogicVM, address: LValue) -> Option<LValue> {
+        Some(match address.num_usize() {
+            Ok(0) => self.position(),
+            _ => LValue::NULL,
+        })
+    }
+
+    fn sensor(&mut self, _: &mut ProcessorState, _: &LogicVM, sensor: LAccess) -> Option<LValue> {
+        self.read_sensor(sensor)
+    }
+}
+
+impl<const N: usize> Sensors for SelectorData<'_, N> {
+    fn sensors<'s>() -> &'s [(LAccess, SensorGetter<Self>)]
+    where
+        Self: 's,
+    {
+        &[(LAccess::MemoryCapacity, |_| 1.into())]
+    }
+}
@@ -0,0 +1,20 @@
+use mindy::{types::LAccess, vm::LValue};
+
+pub type SensorGetter<T> = fn(&mut T) -> LValue;
+
+/// Table of the values a building exposes to `sensor`.
+///
+/// Buildings should forward [`CustomBuildingData::sensor`](mindy::vm::CustomBuildingData::sensor)
+/// to [`Sensors::read_sensor`] instead of matching on [`LAccess`] by hand.
+pub trait Sensors {
+    fn sensors<'s>() -> &'s [(LAccess, SensorGetter<Self>)]
+    where
+        Self: 's;
+
+    fn read_sensor(&mut self, sensor: LAccess) -> Option<LValue> {
+        Self::sensors()
+            .iter()
+            .find(|(access, _)| *access == sensor)
+            .map(|(_, getter)| getter(self))
+    }
+}
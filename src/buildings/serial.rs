@@ -1,9 +1,13 @@
-use alloc::{rc::Rc, string::String};
-use core::cell::RefCell;
+use alloc::{rc::Rc, string::String, vec, vec::Vec};
+use core::{
+    cell::{Cell, RefCell},
+    fmt,
+};
 
 use embassy_executor::SpawnToken;
 use embassy_futures::yield_now;
 use embassy_rp::{peripherals::USB, usb};
+use embassy_time::{Duration, with_timeout};
 use embassy_usb::class::cdc_acm::{self, CdcAcmClass};
 use heapless::Deque;
 use mindy::{
@@ -11,18 +15,69 @@ use mindy::{
     vm::{CustomBuildingData, InstructionResult, LValue, LogicVM, ProcessorState},
 };
 
-use crate::MAX_USB_PACKET_SIZE;
+use super::{
+    AutoFlush, Bridge, ConfigOption, ControlChars, ReadTimeout, SensorGetter, Sensors, Stat,
+};
+use crate::{
+    MAX_USB_PACKET_SIZE, debug_commands::DebugCommands, pixel_stream::PixelStream,
+    task_stats::TaskCounter,
+};
+
+// how long a packet can wait for the host in non-blocking mode before the rest is dropped
+const NON_BLOCKING_TX_TIMEOUT: Duration = Duration::from_millis(100);
+
+// with the keepalive feature, a packet with just ENQ is answered with ACK by the firmware, so
+// hosts can tell it's alive
+const KEEPALIVE: bool = cfg!(feature = "keepalive");
+const KEEPALIVE_PING: u8 = 0x05;
+const KEEPALIVE_ACK: u8 = 0x06;
+
+// output queued past this many bytes is dropped until the tick sends it, so a program (or a draw
+// log dump) that writes faster than the host reads can't fill the heap
+const TX_BUFFER_SIZE: usize = 4096;
 
 #[embassy_executor::task]
 async fn serial_data_task(
     mut rx: cdc_acm::Receiver<'static, usb::Driver<'static, USB>>,
     rx_buf: Rc<RefCell<Deque<u8, MAX_USB_PACKET_SIZE>>>,
+    rx_high_water: Rc<Cell<usize>>,
+    bridge: Bridge,
+    pixel_stream: PixelStream,
+    debug_commands: DebugCommands,
+    // set when a keepalive ping arrives, so the next tick replies
+    keepalive_ack: Rc<Cell<bool>>,
+    task_counter: TaskCounter,
 ) {
     let mut buf = [0; MAX_USB_PACKET_SIZE];
     loop {
+        task_counter.record();
         let n = rx.read_packet(&mut buf).await.unwrap();
         let data = &buf[..n];
 
+        if KEEPALIVE && data == [KEEPALIVE_PING] {
+            keepalive_ack.set(true);
+            continue;
+        }
+
+        if debug_commands.receive(data) {
+            continue;
+        }
+
+        if pixel_stream.receive(data) {
+            while pixel_stream.is_full() {
+                yield_now().await;
+            }
+            continue;
+        }
+
+        if bridge.enabled() {
+            while bridge.has_pending_to_uart() {
+                yield_now().await;
+            }
+            bridge.push_to_uart(data);
+            continue;
+        }
+
         while !rx_buf.borrow().is_empty() {
             yield_now().await;
         }
@@ -31,71 +86,245 @@ async fn serial_data_task(
         for &item in data {
             queue.push_back(item).unwrap();
         }
+        rx_high_water.set(rx_high_water.get().max(queue.len()));
+    }
+}
+
+/// Handle for writing to the USB serial port from outside of mlog.
+#[derive(Clone)]
+pub struct SerialWriter(Rc<RefCell<TxQueue>>);
+
+impl fmt::Write for SerialWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        queue_tx(&self.0, s);
+        Ok(())
+    }
+}
+
+/// Output waiting for a tick to send it.
+#[derive(Default)]
+pub(super) struct TxQueue {
+    text: Option<String>,
+    // most bytes that were queued at once, to check how close it came to TX_BUFFER_SIZE
+    high_water: usize,
+}
+
+impl TxQueue {
+    pub(super) fn take(&mut self) -> Option<String> {
+        self.text.take()
+    }
+
+    pub(super) fn high_water(&self) -> usize {
+        self.high_water
+    }
+
+    pub(super) fn reset_high_water(&mut self) {
+        self.high_water = 0;
     }
 }
 
+/// Appends to queued output instead of replacing it, since firmware messages (eg. a draw log dump)
+/// and mlog share the buffer, and drops whatever doesn't fit in `TX_BUFFER_SIZE`.
+pub(super) fn queue_tx(tx_buf: &RefCell<TxQueue>, text: &str) {
+    let mut tx_buf = tx_buf.borrow_mut();
+    let TxQueue {
+        text: queued,
+        high_water,
+    } = &mut *tx_buf;
+    let queued = queued.get_or_insert_default();
+    let mut n = text.len().min(TX_BUFFER_SIZE.saturating_sub(queued.len()));
+    while !text.is_char_boundary(n) {
+        n -= 1;
+    }
+    queued.push_str(&text[..n]);
+    *high_water = (*high_water).max(queued.len());
+}
+
 pub struct SerialData {
-    tx_buf: Rc<RefCell<Option<String>>>,
+    tx_buf: Rc<RefCell<TxQueue>>,
     rx_buf: Rc<RefCell<Deque<u8, MAX_USB_PACKET_SIZE>>>,
+    rx_high_water: Rc<Cell<usize>>,
+    pixel_stream: PixelStream,
+    auto_flush: AutoFlush,
+    read_timeout: ReadTimeout,
+    non_blocking_tx: Rc<Cell<bool>>,
+    control_chars: ControlChars,
+    bridge: Bridge,
+    enabled: bool,
 }
 
 impl SerialData {
     pub fn new(
         class: CdcAcmClass<'static, usb::Driver<'static, USB>>,
+        pixel_stream: PixelStream,
+        debug_commands: DebugCommands,
+        task_counter: TaskCounter,
+        bridge: Bridge,
     ) -> (Self, SpawnToken<impl Sized>, impl AsyncFnMut()) {
         let (mut tx, rx) = class.split();
 
-        let tx_buf = Rc::new(RefCell::new(None));
+        let tx_buf = Rc::new(RefCell::new(TxQueue::default()));
         let rx_buf = Rc::new(RefCell::new(Deque::new()));
+        let rx_high_water = Rc::new(Cell::new(0));
+        let non_blocking_tx = Rc::new(Cell::new(false));
+        let keepalive_ack = Rc::new(Cell::new(false));
+        let control_chars = ControlChars::default();
 
         (
             Self {
                 tx_buf: tx_buf.clone(),
                 rx_buf: rx_buf.clone(),
+                rx_high_water: rx_high_water.clone(),
+                pixel_stream: pixel_stream.clone(),
+                auto_flush: AutoFlush::new(tx_buf.clone()),
+                read_timeout: ReadTimeout::default(),
+                non_blocking_tx: non_blocking_tx.clone(),
+                control_chars: control_chars.clone(),
+                bridge: bridge.clone(),
+                enabled: true,
             },
-            serial_data_task(rx, rx_buf),
+            serial_data_task(
+                rx,
+                rx_buf,
+                rx_high_water,
+                bridge.clone(),
+                pixel_stream,
+                debug_commands,
+                keepalive_ack.clone(),
+                task_counter,
+            ),
             async move || {
-                if let Some(message) = tx_buf.replace(None) {
-                    let n = message.len().min(MAX_USB_PACKET_SIZE);
-                    tx.write_packet(&message.as_bytes()[..n]).await.unwrap();
-                    if n == MAX_USB_PACKET_SIZE {
-                        tx.write_packet(&[]).await.unwrap();
+                let ack = keepalive_ack.take();
+                let message = tx_buf.borrow_mut().take();
+                let bridged = bridge.take_to_serial();
+                if !ack && message.is_none() && bridged.is_empty() {
+                    return;
+                }
+
+                // without a terminal open, nothing would ever read the packets
+                let non_blocking = non_blocking_tx.get();
+                if non_blocking && !tx.dtr() {
+                    return;
+                }
+
+                // sent first, so it doesn't wait behind a long message
+                let mut data = if ack { vec![KEEPALIVE_ACK] } else { Vec::new() };
+                if let Some(message) = message {
+                    data.extend(control_chars.filter(message).into_bytes());
+                }
+                data.extend(bridged);
+
+                let packets = data.chunks(MAX_USB_PACKET_SIZE);
+                let zlp = (data.len() % MAX_USB_PACKET_SIZE == 0).then_some(&[][..]);
+                for packet in packets.chain(zlp) {
+                    if non_blocking {
+                        match with_timeout(NON_BLOCKING_TX_TIMEOUT, tx.write_packet(packet)).await {
+                            Ok(Ok(())) => {}
+                            // the host went away, so drop the rest of the message
+                            _ => return,
+                        }
+                    } else {
+                        tx.write_packet(packet).await.unwrap();
                     }
                 }
             },
         )
     }
+
+    pub fn writer(&self) -> SerialWriter {
+        SerialWriter(self.tx_buf.clone())
+    }
+
+    /// Allows mlog to send printed lines without calling `printflush` with
+    /// `control config serial @autoFlush 1`.
+    pub fn auto_flush(&self) -> AutoFlush {
+        self.auto_flush.clone()
+    }
 }
 
 impl CustomBuildingData for SerialData {
     fn read(&mut self, _: &mut ProcessorState, _: &LogicVM, address: LValue) -> Option<LValue> {
+        if !self.enabled {
+            return Some(LValue::NULL);
+        }
+
+        match Stat::from_lvalue(&address) {
+            Some(Stat::RxHighWater) => return Some(self.rx_high_water.get().into()),
+            Some(Stat::TxHighWater) => return Some(self.tx_buf.borrow().high_water().into()),
+            _ => {}
+        }
+
+        let Ok(mut i) = address.num_usize() else {
+            return Some(LValue::NULL);
+        };
+
         let mut buf = self.rx_buf.borrow_mut();
-        if let Ok(mut i) = address.num_usize()
-            && i < buf.len()
-        {
+        let value = (i < buf.len()).then(|| {
             // if we read from address i > 0 in the queue, discard all values before i
             while i > 0 {
                 buf.pop_front();
                 i -= 1;
             }
 
-            Some(buf.pop_front().into())
-        } else {
-            Some(LValue::NULL)
-        }
+            buf.pop_front().into()
+        });
+        Some(self.read_timeout.poll(value))
     }
 
     fn printflush(&mut self, state: &mut ProcessorState, _: &LogicVM) -> InstructionResult {
-        self.tx_buf
-            .replace(Some(state.printbuffer.to_string_lossy()));
+        if !self.enabled {
+            return InstructionResult::Ok;
+        }
+
+        queue_tx(&self.tx_buf, &state.printbuffer.to_string_lossy());
         InstructionResult::Yield
     }
 
+    fn control(
+        &mut self,
+        _: &mut ProcessorState,
+        _: &LogicVM,
+        control: LAccess,
+        p1: &LValue,
+        p2: &LValue,
+        _: &LValue,
+    ) -> InstructionResult {
+        match control {
+            LAccess::Enabled => self.enabled = p1.bool(),
+            LAccess::Config => match ConfigOption::from_lvalue(p1) {
+                Some(ConfigOption::AutoFlush) => self.auto_flush.set_enabled(p2.bool()),
+                Some(ConfigOption::Bridge) => self.bridge.set_enabled(p2.bool()),
+                Some(ConfigOption::ControlChars) => self.control_chars.set_mode(p2.num()),
+                Some(ConfigOption::NonBlockingTx) => self.non_blocking_tx.set(p2.bool()),
+                Some(ConfigOption::PixelStream) => self.pixel_stream.set_enabled(p2.bool()),
+                Some(ConfigOption::ReadTimeout) => self.read_timeout.set_timeout_ms(p2.num()),
+                Some(ConfigOption::ResetRxHighWater) => {
+                    self.rx_high_water.set(0);
+                    self.tx_buf.borrow_mut().reset_high_water();
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+        InstructionResult::Ok
+    }
+
     fn sensor(&mut self, _: &mut ProcessorState, _: &LogicVM, sensor: LAccess) -> Option<LValue> {
-        Some(match sensor {
-            LAccess::MemoryCapacity => MAX_USB_PACKET_SIZE.into(),
-            LAccess::BufferSize => self.rx_buf.borrow().len().into(),
-            _ => return None,
-        })
+        self.read_sensor(sensor)
+    }
+}
+
+impl Sensors for SerialData {
+    fn sensors<'s>() -> &'s [(LAccess, SensorGetter<Self>)]
+    where
+        Self: 's,
+    {
+        &[
+            (LAccess::Enabled, |data| data.enabled.into()),
+            (LAccess::MemoryCapacity, |_| MAX_USB_PACKET_SIZE.into()),
+            (LAccess::BufferSize, |data| {
+                data.rx_buf.borrow().len().into()
+            }),
+        ]
     }
 }
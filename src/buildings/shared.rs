@@ -0,0 +1,79 @@
+use alloc::rc::Rc;
+use core::cell::{RefCell, RefMut};
+
+use mindy::{
+    types::LAccess,
+    vm::{CustomBuildingData, InstructionResult, LValue, LogicVM, ProcessorState},
+};
+
+/// Building data that can also be used outside of the VM, eg. from the instruction hook.
+///
+/// The VM never calls into a building while the instruction hook is running, so borrowing it from
+/// the hook can't conflict with the VM.
+pub struct SharedBuilding<B>(Rc<RefCell<B>>);
+
+impl<B> SharedBuilding<B> {
+    pub fn new(data: B) -> Self {
+        Self(Rc::new(RefCell::new(data)))
+    }
+
+    pub fn borrow_mut(&self) -> RefMut<'_, B> {
+        self.0.borrow_mut()
+    }
+}
+
+impl<B> Clone for SharedBuilding<B> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<B: CustomBuildingData> CustomBuildingData for SharedBuilding<B> {
+    fn read(
+        &mut self,
+        state: &mut ProcessorState,
+        vm: &LogicVM,
+        address: LValue,
+    ) -> Option<LValue> {
+        self.borrow_mut().read(state, vm, address)
+    }
+
+    fn write(
+        &mut self,
+        state: &mut ProcessorState,
+        vm: &LogicVM,
+        address: LValue,
+        value: LValue,
+    ) -> InstructionResult {
+        self.borrow_mut().write(state, vm, address, value)
+    }
+
+    fn printflush(&mut self, state: &mut ProcessorState, vm: &LogicVM) -> InstructionResult {
+        self.borrow_mut().printflush(state, vm)
+    }
+
+    fn drawflush(&mut self, state: &mut ProcessorState, vm: &LogicVM) -> InstructionResult {
+        self.borrow_mut().drawflush(state, vm)
+    }
+
+    fn control(
+        &mut self,
+        state: &mut ProcessorState,
+        vm: &LogicVM,
+        control: LAccess,
+        p1: &LValue,
+        p2: &LValue,
+        p3: &LValue,
+    ) -> InstructionResult {
+        self.borrow_mut().control(state, vm, control, p1, p2, p3)
+    }
+
+    fn sensor(
+        &mut self,
+        state: &mut ProcessorState,
+        vm: &LogicVM,
+        sensor: LAccess,
+    ) -> Option<LValue> {
+        self.borrow_mut().sensor(state, vm, sensor)
+    }
+}
@@ -0,0 +1,136 @@
+use alloc::{rc::Rc, vec::Vec};
+use core::cell::RefCell;
+
+use embassy_time::{Duration, Instant};
+use mindy::{
+    types::LAccess,
+    vm::{CustomBuildingData, InstructionResult, LValue, LogicVM, ProcessorState},
+};
+
+use super::{ConfigOption, SensorGetter, Sensors, Stat};
+use crate::{
+    Program,
+    heap::{self, HeapMonitor},
+    platform::reboot_to_program,
+    task_stats::TaskCounter,
+};
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, Default)]
+struct Sample {
+    polls_per_second: f64,
+    longest_gap_ms: f64,
+}
+
+/// Reports how often tasks are being polled, to find out which ones are being starved.
+///
+/// Also handles firmware-wide options that don't belong to any peripheral, eg. `@runProgram`.
+///
+/// For the task at index `i`, address `2 * i` reads the number of polls per second, and address
+/// `2 * i + 1` reads the longest time between polls in ms. Both are sampled every second. Note
+/// that a task waiting for something (eg. a USB packet) also isn't polled.
+pub struct TaskStatsData {
+    samples: Rc<RefCell<Vec<Sample>>>,
+    heap_monitor: Option<HeapMonitor>,
+}
+
+impl TaskStatsData {
+    pub fn new(counters: Vec<TaskCounter>) -> (Self, impl FnMut()) {
+        let samples = Rc::new(RefCell::new(alloc::vec![Sample::default(); counters.len()]));
+        let mut last_sample = Instant::now();
+        (
+            Self {
+                samples: samples.clone(),
+                heap_monitor: None,
+            },
+            move || {
+                let elapsed = last_sample.elapsed();
+                if elapsed < SAMPLE_INTERVAL {
+                    return;
+                }
+                last_sample = Instant::now();
+
+                for (counter, sample) in counters.iter().zip(samples.borrow_mut().iter_mut()) {
+                    let (polls, longest_gap) = counter.sample();
+                    *sample = Sample {
+                        polls_per_second: polls as f64 * 1_000_000. / elapsed.as_micros() as f64,
+                        longest_gap_ms: longest_gap.as_micros() as f64 / 1000.,
+                    };
+                }
+            },
+        )
+    }
+
+    /// Allows mlog to configure what happens when the heap is nearly full with
+    /// `control config tasks @heapThreshold <bytes>` and `@heapAction <action>`.
+    pub fn with_heap_monitor(mut self, heap_monitor: HeapMonitor) -> Self {
+        self.heap_monitor = Some(heap_monitor);
+        self
+    }
+}
+
+impl CustomBuildingData for TaskStatsData {
+    fn read(&mut self, _: &mut ProcessorState, _: &LogicVM, address: LValue) -> Option<LValue> {
+        if let Some(Stat::HeapFree) = Stat::from_lvalue(&address) {
+            return Some(heap::free().into());
+        }
+
+        let samples = self.samples.borrow();
+        Some(
+            match address.num_usize().map(|i| (samples.get(i / 2), i % 2)) {
+                Ok((Some(sample), 0)) => sample.polls_per_second.into(),
+                Ok((Some(sample), _)) => sample.longest_gap_ms.into(),
+                _ => LValue::NULL,
+            },
+        )
+    }
+
+    fn control(
+        &mut self,
+        _: &mut ProcessorState,
+        _: &LogicVM,
+        control: LAccess,
+        p1: &LValue,
+        p2: &LValue,
+        _: &LValue,
+    ) -> InstructionResult {
+        if control != LAccess::Config {
+            return InstructionResult::Ok;
+        }
+
+        match (ConfigOption::from_lvalue(p1), &self.heap_monitor) {
+            (Some(ConfigOption::HeapThreshold), Some(heap_monitor)) => {
+                heap_monitor.set_threshold(p2.num());
+            }
+            (Some(ConfigOption::HeapAction), Some(heap_monitor)) => {
+                heap_monitor.set_action(p2.num());
+            }
+            (Some(ConfigOption::RunProgram), _) => {
+                // programs that need a bigger heap than this build has are ignored
+                if let Ok(index) = p2.num_usize()
+                    && Program::runnable(index).is_some()
+                {
+                    reboot_to_program(index);
+                }
+            }
+            _ => {}
+        }
+        InstructionResult::Ok
+    }
+
+    fn sensor(&mut self, _: &mut ProcessorState, _: &LogicVM, sensor: LAccess) -> Option<LValue> {
+        self.read_sensor(sensor)
+    }
+}
+
+impl Sensors for TaskStatsData {
+    fn sensors<'s>() -> &'s [(LAccess, SensorGetter<Self>)]
+    where
+        Self: 's,
+    {
+        &[(LAccess::MemoryCapacity, |data| {
+            (data.samples.borrow().len() * 2).into()
+        })]
+    }
+}
@@ -0,0 +1,63 @@
+use embassy_rp::adc::{Adc, Blocking, Channel};
+use mindy::{
+    types::LAccess,
+    vm::{CustomBuildingData, LValue, LogicVM, ProcessorState},
+};
+
+use super::{SensorGetter, Sensors};
+
+// from the RP2040 datasheet, section 4.9.5
+const ADC_VOLTS_PER_COUNT: f64 = 3.3 / 4096.;
+const VOLTS_AT_27C: f64 = 0.706;
+const VOLTS_PER_DEGREE: f64 = 0.001721;
+
+/// The chip's internal temperature sensor, on ADC channel 4.
+///
+/// Read 0 or sense `@heat` for the temperature in C. It measures the die rather than the air, and
+/// each chip is a few degrees off, so it's better for noticing changes than for absolute readings.
+pub struct TempSensorData<'a> {
+    adc: Adc<'a, Blocking>,
+    channel: Channel<'a>,
+}
+
+impl<'a> TempSensorData<'a> {
+    pub fn new(adc: Adc<'a, Blocking>, channel: Channel<'a>) -> Self {
+        Self { adc, channel }
+    }
+
+    fn read_celsius(&mut self) -> LValue {
+        self.adc
+            .blocking_read(&mut self.channel)
+            .map_or(LValue::NULL, |raw| to_celsius(raw).into())
+    }
+}
+
+fn to_celsius(raw: u16) -> f64 {
+    let volts = raw as f64 * ADC_VOLTS_PER_COUNT;
+    27. - (volts - VOLTS_AT_27C) / VOLTS_PER_DEGREE
+}
+
+impl CustomBuildingData for TempSensorData<'_> {
+    fn read(&mut self, _: &mut ProcessorState, _: &LogicVM, address: LValue) -> Option<LValue> {
+        Some(match address.num_usize() {
+            Ok(0) => self.read_celsius(),
+            _ => LValue::NULL,
+        })
+    }
+
+    fn sensor(&mut self, _: &mut ProcessorState, _: &LogicVM, sensor: LAccess) -> Option<LValue> {
+        self.read_sensor(sensor)
+    }
+}
+
+impl Sensors for TempSensorData<'_> {
+    fn sensors<'s>() -> &'s [(LAccess, SensorGetter<Self>)]
+    where
+        Self: 's,
+    {
+        &[
+            (LAccess::Heat, |data| data.read_celsius()),
+            (LAccess::MemoryCapacity, |_| 1.into()),
+        ]
+    }
+}
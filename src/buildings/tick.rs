@@ -0,0 +1,178 @@
+use alloc::rc::Rc;
+use core::cell::Cell;
+
+use embassy_time::{Duration, Instant, TICK_HZ};
+use mindy::{
+    types::LAccess,
+    vm::{CustomBuildingData, InstructionResult, LValue, LogicVM, ProcessorState},
+};
+
+use super::{ConfigOption, SensorGetter, Sensors};
+
+const DEFAULT_FREQUENCY: f64 = 1000.;
+
+// longer sleeps are shortened to this, so converting them can't overflow
+const MAX_SLEEP: Duration = Duration::from_secs(24 * 60 * 60);
+
+// how long processor ticks are counted for before @tick's tick rate is updated
+const TICK_RATE_WINDOW: Duration = Duration::from_secs(1);
+
+struct ClockState {
+    ticks: Cell<u32>,
+    window_start: Cell<Instant>,
+    tick_rate: Cell<f64>,
+    sleep_until: Cell<Option<Instant>>,
+}
+
+/// Shared between the main loop, which runs the processor's ticks, and the `tick` building, so mlog
+/// can see how often the processor runs and pause it for less than a tick.
+#[derive(Clone)]
+pub struct ProcessorClock(Rc<ClockState>);
+
+impl Default for ProcessorClock {
+    fn default() -> Self {
+        Self(Rc::new(ClockState {
+            ticks: Cell::new(0),
+            window_start: Cell::new(Instant::now()),
+            tick_rate: Cell::new(0.),
+            sleep_until: Cell::new(None),
+        }))
+    }
+}
+
+impl ProcessorClock {
+    /// Called by the main loop after each processor tick.
+    pub fn record_tick(&self) {
+        let ticks = self.0.ticks.get() + 1;
+        let elapsed = self.0.window_start.get().elapsed();
+        if elapsed >= TICK_RATE_WINDOW {
+            self.0
+                .tick_rate
+                .set(ticks as f64 * TICK_HZ as f64 / elapsed.as_ticks() as f64);
+            self.0.ticks.set(0);
+            self.0.window_start.set(Instant::now());
+        } else {
+            self.0.ticks.set(ticks);
+        }
+    }
+
+    /// Returns how long the processor should keep sleeping for, if it's sleeping.
+    pub fn sleep_remaining(&self) -> Option<Duration> {
+        let remaining = self
+            .0
+            .sleep_until
+            .get()?
+            .checked_duration_since(Instant::now());
+        if remaining.is_none() {
+            self.0.sleep_until.set(None);
+        }
+        remaining
+    }
+
+    fn sleep(&self, time: Duration) {
+        let until = Instant::now().checked_add(time).unwrap_or(Instant::MAX);
+        self.0.sleep_until.set(Some(until));
+    }
+}
+
+/// Steady tick counter for scheduling, independent of how fast the processor runs.
+///
+/// - Address 0: number of ticks since the frequency was last set.
+/// - Address 1: number of ticks since the last read of this address.
+/// - Address 2: number of processor ticks per second, updated every second.
+///
+/// Ticks are counted from the hardware timer rather than by a task, so none are missed while the
+/// processor is busy.
+pub struct TickData {
+    frequency: f64,
+    start: Instant,
+    last_read: u64,
+    clock: Option<ProcessorClock>,
+}
+
+impl Default for TickData {
+    fn default() -> Self {
+        Self {
+            frequency: DEFAULT_FREQUENCY,
+            start: Instant::now(),
+            last_read: 0,
+            clock: None,
+        }
+    }
+}
+
+impl TickData {
+    /// Allows mlog to read the processor's tick rate, and to pause it for a number of us with
+    /// `control config tick @sleep <us>`.
+    pub fn with_processor_clock(mut self, clock: ProcessorClock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    fn ticks(&self) -> u64 {
+        (self.start.elapsed().as_ticks() as f64 * self.frequency / TICK_HZ as f64) as u64
+    }
+}
+
+impl CustomBuildingData for TickData {
+    fn read(&mut self, _: &mut ProcessorState, _: &LogicVM, address: LValue) -> Option<LValue> {
+        Some(match address.num_usize() {
+            Ok(0) => (self.ticks() as f64).into(),
+            Ok(1) => {
+                let ticks = self.ticks();
+                let new_ticks = ticks - self.last_read;
+                self.last_read = ticks;
+                (new_ticks as f64).into()
+            }
+            Ok(2) => self
+                .clock
+                .as_ref()
+                .map_or(LValue::NULL, |clock| clock.0.tick_rate.get().into()),
+            _ => LValue::NULL,
+        })
+    }
+
+    fn control(
+        &mut self,
+        _: &mut ProcessorState,
+        _: &LogicVM,
+        control: LAccess,
+        p1: &LValue,
+        p2: &LValue,
+        _: &LValue,
+    ) -> InstructionResult {
+        if control != LAccess::Config {
+            return InstructionResult::Ok;
+        }
+        match ConfigOption::from_lvalue(p1) {
+            Some(ConfigOption::TickFrequency) => {
+                self.frequency = p2.num().max(0.);
+                self.start = Instant::now();
+                self.last_read = 0;
+            }
+            // ends the processor's tick, and the main loop skips its ticks until it wakes up
+            Some(ConfigOption::Sleep) => {
+                if let Some(clock) = &self.clock {
+                    let micros = p2.num().clamp(0., MAX_SLEEP.as_micros() as f64);
+                    clock.sleep(Duration::from_micros(micros as u64));
+                    return InstructionResult::Yield;
+                }
+            }
+            _ => {}
+        }
+        InstructionResult::Ok
+    }
+
+    fn sensor(&mut self, _: &mut ProcessorState, _: &LogicVM, sensor: LAccess) -> Option<LValue> {
+        self.read_sensor(sensor)
+    }
+}
+
+impl Sensors for TickData {
+    fn sensors<'s>() -> &'s [(LAccess, SensorGetter<Self>)]
+    where
+        Self: 's,
+    {
+        &[(LAccess::MemoryCapacity, |_| 3.into())]
+    }
+}
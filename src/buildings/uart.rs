@@ -1,69 +1,252 @@
-use alloc::{rc::Rc, string::String};
-use core::cell::RefCell;
+use alloc::rc::Rc;
+use core::cell::{Cell, RefCell};
 
-use embassy_rp::uart::{BufferedUart, BufferedUartRx};
+use embassy_rp::uart::{self, BufferedUart, BufferedUartRx};
 use embedded_io::{Read, ReadReady};
 use embedded_io_async::Write;
+use heapless::Deque;
 use mindy::{
     types::LAccess,
     vm::{CustomBuildingData, InstructionResult, LValue, LogicVM, ProcessorState},
 };
 
+use super::{
+    AutoFlush, Bridge, ConfigOption, ControlChars, ReadTimeout, SensorGetter, Sensors, Stat,
+    serial::{TxQueue, queue_tx},
+};
 use crate::UART_BUFFER_SIZE;
 
+/// Returns the width in bytes of the value read from an address, and whether it's big-endian.
+fn word_format(address: i32) -> Option<(usize, bool)> {
+    match address {
+        0 => Some((1, false)),
+        1 => Some((2, false)),
+        2 => Some((2, true)),
+        3 => Some((4, false)),
+        4 => Some((4, true)),
+        _ => None,
+    }
+}
+
+/// Counts of bytes received with the wrong framing or parity, which usually means the baud rate or
+/// the frame format doesn't match the other device.
+#[derive(Default)]
+struct RxErrors {
+    framing: Cell<u32>,
+    parity: Cell<u32>,
+}
+
+impl RxErrors {
+    fn record(&self, err: uart::Error) {
+        let count = match err {
+            uart::Error::Framing => &self.framing,
+            uart::Error::Parity => &self.parity,
+            _ => return,
+        };
+        count.set(count.get().saturating_add(1));
+    }
+
+    fn reset(&self) {
+        self.framing.set(0);
+        self.parity.set(0);
+    }
+}
+
+/// UART0 on GP0/GP1.
+///
+/// Address 0 reads a byte. Addresses 1 and 2 read a 16-bit little/big-endian word, and addresses
+/// 3 and 4 read a 32-bit little/big-endian word. Words are only returned once all of their bytes
+/// have arrived.
 pub struct UartData {
-    tx_buf: Rc<RefCell<Option<String>>>,
-    rx: BufferedUartRx,
+    tx_buf: Rc<RefCell<TxQueue>>,
+    // shared with the tick, which reads from it in bridge mode
+    rx: Rc<RefCell<BufferedUartRx>>,
+    // received bytes are moved here when reading, so we can tell how many were waiting
+    rx_buf: Deque<u8, UART_BUFFER_SIZE>,
+    rx_high_water: usize,
+    // shared with the tick, since it reads in bridge mode
+    rx_errors: Rc<RxErrors>,
+    auto_flush: AutoFlush,
+    read_timeout: ReadTimeout,
+    control_chars: ControlChars,
+    bridge: Bridge,
+    enabled: bool,
 }
 
 impl UartData {
-    pub fn new(uart: BufferedUart) -> (Self, impl AsyncFnMut()) {
+    pub fn new(uart: BufferedUart, bridge: Bridge) -> (Self, impl AsyncFnMut()) {
         let (mut tx, rx) = uart.split();
-        let tx_buf = Rc::new(RefCell::new(None));
+        let rx = Rc::new(RefCell::new(rx));
+        let tx_buf = Rc::new(RefCell::new(TxQueue::default()));
+        let rx_errors = Rc::new(RxErrors::default());
+        let control_chars = ControlChars::default();
         (
             Self {
                 tx_buf: tx_buf.clone(),
-                rx,
+                rx: rx.clone(),
+                rx_buf: Deque::new(),
+                rx_high_water: 0,
+                rx_errors: rx_errors.clone(),
+                auto_flush: AutoFlush::new(tx_buf.clone()),
+                read_timeout: ReadTimeout::default(),
+                control_chars: control_chars.clone(),
+                bridge: bridge.clone(),
+                enabled: true,
             },
             async move || {
-                if let Some(message) = tx_buf.replace(None) {
+                let message = tx_buf.borrow_mut().take();
+                if let Some(message) = message {
+                    let message = control_chars.filter(message);
                     tx.write_all(message.as_bytes()).await.unwrap();
                 }
+
+                if !bridge.enabled() {
+                    return;
+                }
+                let data = bridge.take_to_uart();
+                if !data.is_empty() {
+                    tx.write_all(&data).await.unwrap();
+                }
+                // wait for serial to send the last batch, so a slow host can't use up the heap
+                if !bridge.has_pending_to_serial() {
+                    let mut rx = rx.borrow_mut();
+                    let mut buf = [0; UART_BUFFER_SIZE];
+                    if let Ok(true) = rx.read_ready() {
+                        match rx.read(&mut buf) {
+                            Ok(n) => bridge.push_to_serial(&buf[..n]),
+                            Err(err) => rx_errors.record(err),
+                        }
+                    }
+                }
             },
         )
     }
+
+    /// Allows mlog to send printed lines without calling `printflush` with
+    /// `control config uart0 @autoFlush 1`.
+    pub fn auto_flush(&self) -> AutoFlush {
+        self.auto_flush.clone()
+    }
+
+    fn receive(&mut self) {
+        let mut rx = self.rx.borrow_mut();
+        let mut buf = [0; UART_BUFFER_SIZE];
+        while let free @ 1.. = self.rx_buf.capacity() - self.rx_buf.len()
+            && let Ok(true) = rx.read_ready()
+        {
+            // the error is cleared once it's been returned, so the next read gets the data after it
+            let n = match rx.read(&mut buf[..free]) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(err) => {
+                    self.rx_errors.record(err);
+                    continue;
+                }
+            };
+            for &byte in &buf[..n] {
+                self.rx_buf.push_back(byte).unwrap();
+            }
+        }
+        self.rx_high_water = self.rx_high_water.max(self.rx_buf.len());
+    }
 }
 
 impl CustomBuildingData for UartData {
     fn read(&mut self, _: &mut ProcessorState, _: &LogicVM, address: LValue) -> Option<LValue> {
-        let mut buf = [0; 1];
-        if address.numi() == 0
-            && let Ok(true) = self.rx.read_ready()
-            && let Ok(n) = self.rx.read(&mut buf)
-            && n > 0
-        {
-            Some(buf[0].into())
-        } else {
-            Some(LValue::NULL)
+        if !self.enabled {
+            return Some(LValue::NULL);
+        }
+        match Stat::from_lvalue(&address) {
+            Some(Stat::RxHighWater) => return Some(self.rx_high_water.into()),
+            Some(Stat::TxHighWater) => return Some(self.tx_buf.borrow().high_water().into()),
+            Some(Stat::FramingErrors) => {
+                return Some(f64::from(self.rx_errors.framing.get()).into());
+            }
+            Some(Stat::ParityErrors) => return Some(f64::from(self.rx_errors.parity.get()).into()),
+            _ => {}
+        }
+        let Some((width, big_endian)) = word_format(address.numi()) else {
+            return Some(LValue::NULL);
+        };
+
+        // in bridge mode, received bytes belong to serial
+        if !self.bridge.enabled() {
+            self.receive();
         }
+
+        let value = (self.rx_buf.len() >= width).then(|| {
+            let mut bytes = [0; 4];
+            for byte in &mut bytes[..width] {
+                *byte = self.rx_buf.pop_front().unwrap();
+            }
+            let bytes = &bytes[..width];
+            let word = if big_endian {
+                bytes.iter().fold(0u32, |word, &b| (word << 8) | b as u32)
+            } else {
+                bytes
+                    .iter()
+                    .rev()
+                    .fold(0u32, |word, &b| (word << 8) | b as u32)
+            };
+            f64::from(word).into()
+        });
+        Some(self.read_timeout.poll(value))
     }
 
     fn printflush(&mut self, state: &mut ProcessorState, _: &LogicVM) -> InstructionResult {
-        self.tx_buf
-            .replace(Some(state.printbuffer.to_string_lossy()));
+        if !self.enabled {
+            return InstructionResult::Ok;
+        }
+
+        queue_tx(&self.tx_buf, &state.printbuffer.to_string_lossy());
         InstructionResult::Yield
     }
 
+    fn control(
+        &mut self,
+        _: &mut ProcessorState,
+        _: &LogicVM,
+        control: LAccess,
+        p1: &LValue,
+        p2: &LValue,
+        _: &LValue,
+    ) -> InstructionResult {
+        match control {
+            LAccess::Enabled => self.enabled = p1.bool(),
+            LAccess::Config => match ConfigOption::from_lvalue(p1) {
+                Some(ConfigOption::AutoFlush) => self.auto_flush.set_enabled(p2.bool()),
+                Some(ConfigOption::Bridge) => self.bridge.set_enabled(p2.bool()),
+                Some(ConfigOption::ControlChars) => self.control_chars.set_mode(p2.num()),
+                Some(ConfigOption::ReadTimeout) => self.read_timeout.set_timeout_ms(p2.num()),
+                Some(ConfigOption::ResetRxErrors) => self.rx_errors.reset(),
+                Some(ConfigOption::ResetRxHighWater) => {
+                    self.rx_high_water = 0;
+                    self.tx_buf.borrow_mut().reset_high_water();
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+        InstructionResult::Ok
+    }
+
     fn sensor(&mut self, _: &mut ProcessorState, _: &LogicVM, sensor: LAccess) -> Option<LValue> {
-        Some(match sensor {
-            LAccess::MemoryCapacity => UART_BUFFER_SIZE.into(),
-            LAccess::BufferSize => if let Ok(true) = self.rx.read_ready() {
-                1
-            } else {
-                0
-            }
-            .into(),
-            _ => return None,
-        })
+        self.read_sensor(sensor)
+    }
+}
+
+impl Sensors for UartData {
+    fn sensors<'s>() -> &'s [(LAccess, SensorGetter<Self>)]
+    where
+        Self: 's,
+    {
+        &[
+            (LAccess::Enabled, |data| data.enabled.into()),
+            (LAccess::MemoryCapacity, |_| UART_BUFFER_SIZE.into()),
+            (LAccess::BufferSize, |data| {
+                let ready = matches!(data.rx.borrow_mut().read_ready(), Ok(true));
+                (data.rx_buf.len() + ready as usize).into()
+            }),
+        ]
     }
 }
@@ -0,0 +1,103 @@
+use embassy_rp::gpio::{Input, Output};
+use embassy_time::{Duration, Instant, block_for};
+use mindy::{
+    types::LAccess,
+    vm::{CustomBuildingData, LValue, LogicVM, ProcessorState},
+};
+
+use super::{SensorGetter, Sensors};
+
+const TRIGGER_PULSE: Duration = Duration::from_micros(10);
+// ~4m (the sensor's max range) takes about 23ms round trip
+const ECHO_TIMEOUT: Duration = Duration::from_millis(30);
+// the datasheet recommends at least 60ms between measurements
+const MEASUREMENT_INTERVAL: Duration = Duration::from_millis(60);
+
+// speed of sound at ~20C, halved because the echo time is a round trip
+const CM_PER_MICRO: f64 = 0.0343 / 2.;
+
+/// HC-SR04 ultrasonic distance sensor.
+///
+/// Address 0 reads the distance in cm, address 1 reads it in mm. Both read null if no echo was
+/// received.
+pub struct UltrasonicData<'a> {
+    trigger: Output<'a>,
+    echo: Input<'a>,
+    last_measurement: Option<(Instant, Option<Duration>)>,
+}
+
+impl<'a> UltrasonicData<'a> {
+    pub fn new(mut trigger: Output<'a>, echo: Input<'a>) -> Self {
+        trigger.set_low();
+        Self {
+            trigger,
+            echo,
+            last_measurement: None,
+        }
+    }
+
+    fn echo_time(&mut self) -> Option<Duration> {
+        if let Some((time, echo)) = self.last_measurement
+            && time.elapsed() < MEASUREMENT_INTERVAL
+        {
+            return echo;
+        }
+
+        let echo = self.measure();
+        self.last_measurement = Some((Instant::now(), echo));
+        echo
+    }
+
+    // this intentionally blocks instead of using a task so that the executor can't delay us
+    // between the echo edges
+    fn measure(&mut self) -> Option<Duration> {
+        self.trigger.set_high();
+        block_for(TRIGGER_PULSE);
+        self.trigger.set_low();
+
+        let deadline = Instant::now() + ECHO_TIMEOUT;
+
+        while self.echo.is_low() {
+            if Instant::now() >= deadline {
+                return None;
+            }
+        }
+
+        let start = Instant::now();
+        while self.echo.is_high() {
+            if Instant::now() >= deadline {
+                return None;
+            }
+        }
+
+        Some(start.elapsed())
+    }
+}
+
+impl CustomBuildingData for UltrasonicData<'_> {
+    fn read(&mut self, _: &mut ProcessorState, _: &LogicVM, address: LValue) -> Option<LValue> {
+        let scale = match address.num_usize() {
+            Ok(0) => 1.,
+            Ok(1) => 10.,
+            _ => return Some(LValue::NULL),
+        };
+
+        Some(match self.echo_time() {
+            Some(echo) => (echo.as_micros() as f64 * CM_PER_MICRO * scale).into(),
+            None => LValue::NULL,
+        })
+    }
+
+    fn sensor(&mut self, _: &mut ProcessorState, _: &LogicVM, sensor: LAccess) -> Option<LValue> {
+        self.read_sensor(sensor)
+    }
+}
+
+impl Sensors for UltrasonicData<'_> {
+    fn sensors<'s>() -> &'s [(LAccess, SensorGetter<Self>)]
+    where
+        Self: 's,
+    {
+        &[(LAccess::MemoryCapacity, |_| 2.into())]
+    }
+}
@@ -0,0 +1,53 @@
+use alloc::rc::Rc;
+use core::cell::Cell;
+
+use embedded_hal::spi::{ErrorType, Operation, SpiDevice};
+
+/// Shared count of the bytes written by a [`CountingSpiDevice`].
+#[derive(Clone, Default)]
+pub struct ByteCounter(Rc<Cell<u64>>);
+
+impl ByteCounter {
+    pub fn get(&self) -> u64 {
+        self.0.get()
+    }
+}
+
+/// SPI device wrapper that counts how many bytes are written to the device.
+pub struct CountingSpiDevice<T> {
+    inner: T,
+    counter: ByteCounter,
+}
+
+impl<T> CountingSpiDevice<T> {
+    pub fn new(inner: T) -> (Self, ByteCounter) {
+        let counter = ByteCounter::default();
+        (
+            Self {
+                inner,
+                counter: counter.clone(),
+            },
+            counter,
+        )
+    }
+}
+
+impl<T: ErrorType> ErrorType for CountingSpiDevice<T> {
+    type Error = T::Error;
+}
+
+impl<T: SpiDevice> SpiDevice for CountingSpiDevice<T> {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+        let bytes = operations
+            .iter()
+            .map(|operation| match operation {
+                Operation::Write(buf) | Operation::TransferInPlace(buf) => buf.len(),
+                Operation::Transfer(_, write) => write.len(),
+                Operation::Read(_) | Operation::DelayNs(_) => 0,
+            })
+            .sum::<usize>();
+
+        self.counter.0.set(self.counter.get() + bytes as u64);
+        self.inner.transaction(operations)
+    }
+}
@@ -41,3 +41,103 @@ pub static ST7789VW_DISPLAY: Block = Block {
     id: -5,
     ..DEFAULT
 };
+
+pub static TASK_STATS: Block = Block {
+    name: multistr!("task-stats"),
+    id: -8,
+    ..DEFAULT
+};
+
+#[cfg(feature = "ultrasonic")]
+pub static ULTRASONIC: Block = Block {
+    name: multistr!("hc-sr04"),
+    id: -6,
+    ..DEFAULT
+};
+
+#[cfg(feature = "dht")]
+pub static DHT: Block = Block {
+    name: multistr!("dht"),
+    id: -7,
+    ..DEFAULT
+};
+
+#[cfg(feature = "one_wire")]
+pub static ONE_WIRE: Block = Block {
+    name: multistr!("one-wire"),
+    id: -9,
+    ..DEFAULT
+};
+
+pub static FLASH: Block = Block {
+    name: multistr!("flash-info"),
+    id: -10,
+    ..DEFAULT
+};
+
+#[cfg(feature = "retained")]
+pub static RETAINED: Block = Block {
+    name: multistr!("retained-memory"),
+    id: -11,
+    ..DEFAULT
+};
+
+pub static TICK: Block = Block {
+    name: multistr!("tick-timer"),
+    id: -12,
+    ..DEFAULT
+};
+
+pub static FRAMES: Block = Block {
+    name: multistr!("frame-counter"),
+    id: -13,
+    ..DEFAULT
+};
+
+pub static IMAGE_BANK: Block = Block {
+    name: multistr!("image-bank"),
+    id: -14,
+    ..DEFAULT
+};
+
+#[cfg(feature = "joystick")]
+pub static JOYSTICK: Block = Block {
+    name: multistr!("joystick"),
+    id: -15,
+    ..DEFAULT
+};
+
+#[cfg(feature = "ssd1306")]
+pub static SSD1306_DISPLAY: Block = Block {
+    name: multistr!("ssd1306-display"),
+    id: -16,
+    ..DEFAULT
+};
+
+#[cfg(feature = "ir")]
+pub static IR: Block = Block {
+    name: multistr!("ir-transmitter"),
+    id: -17,
+    ..DEFAULT
+};
+
+#[cfg(feature = "temp_sensor")]
+pub static TEMP_SENSOR: Block = Block {
+    name: multistr!("temperature-sensor"),
+    id: -18,
+    ..DEFAULT
+};
+
+#[cfg(feature = "selector")]
+pub static SELECTOR: Block = Block {
+    name: multistr!("selector"),
+    id: -19,
+    ..DEFAULT
+};
+
+#[cfg(feature = "neopixel")]
+pub static NEOPIXEL: Block = Block {
+    name: multistr!("neopixel"),
+    id: -20,
+    ..DEFAULT
+};
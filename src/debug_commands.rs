@@ -0,0 +1,113 @@
+use alloc::rc::Rc;
+use core::{
+    cell::{Cell, RefCell},
+    fmt::Write,
+    str,
+};
+
+use mindy::vm::{LValue, ProcessorState};
+use widestring::U16String;
+
+#[cfg(feature = "profile")]
+use crate::profiler::Profiler;
+use crate::{PROGRAMS, Program, platform::reboot_to_program};
+
+// data link escape, which mlog programs are unlikely to send
+const COMMAND_PREFIX: u8 = 0x10;
+
+struct Watch {
+    name: U16String,
+    value: Option<LValue>,
+}
+
+#[derive(Default)]
+struct State {
+    dump_variables: Cell<bool>,
+    watch: RefCell<Option<Watch>>,
+    #[cfg(feature = "profile")]
+    dump_profile: Cell<bool>,
+    #[cfg(feature = "profile")]
+    profiler: Profiler,
+}
+
+/// Debugging commands sent from the host over USB serial, bypassing mlog.
+///
+/// Each command is a USB packet starting with `0x10` followed by the command name:
+///
+/// - `vars`: write every variable in the processor to serial as `name=value` lines.
+/// - `run <name>`: reboot into another embedded program.
+/// - `watch <name>`: write `name: old -> new` to serial whenever the variable's value changes.
+/// - `unwatch`: stop watching the variable.
+/// - `profile`: write the time spent per opcode to serial (requires the `profile` feature).
+/// - `profile reset`: clear the recorded times.
+#[derive(Clone, Default)]
+pub struct DebugCommands(Rc<State>);
+
+impl DebugCommands {
+    /// Returns true if the packet was a debug command, and shouldn't be passed to mlog.
+    pub fn receive(&self, data: &[u8]) -> bool {
+        let Some((&COMMAND_PREFIX, command)) = data.split_first() else {
+            return false;
+        };
+
+        match command.trim_ascii() {
+            b"vars" => self.0.dump_variables.set(true),
+            b"unwatch" => *self.0.watch.borrow_mut() = None,
+            #[cfg(feature = "profile")]
+            b"profile" => self.0.dump_profile.set(true),
+            #[cfg(feature = "profile")]
+            b"profile reset" => self.0.profiler.reset(),
+            command => {
+                if let Some(name) = command.strip_prefix(b"run ")
+                    && let Some(index) = PROGRAMS
+                        .iter()
+                        .position(|program| program.name.as_bytes() == name)
+                    && Program::runnable(index).is_some()
+                {
+                    reboot_to_program(index);
+                } else if let Some(name) = command.strip_prefix(b"watch ")
+                    && let Ok(name) = str::from_utf8(name)
+                {
+                    *self.0.watch.borrow_mut() = Some(Watch {
+                        name: U16String::from_str(name),
+                        value: None,
+                    });
+                }
+            }
+        }
+        true
+    }
+
+    #[cfg(feature = "profile")]
+    pub fn profiler(&self) -> Profiler {
+        self.0.profiler.clone()
+    }
+
+    /// Called from the instruction hook, where the processor's variables are available.
+    pub fn run(&self, state: &ProcessorState, writer: &mut impl Write) {
+        if self.0.dump_variables.take() {
+            for (name, value) in state.variables() {
+                writeln!(writer, "{}={value}", name.display()).ok();
+            }
+        }
+
+        if let Some(watch) = &mut *self.0.watch.borrow_mut()
+            && let Some((name, value)) = state
+                .variables()
+                .find(|(name, _)| *name == watch.name.as_ustr())
+            && watch.value.as_ref() != Some(&value)
+        {
+            match &watch.value {
+                Some(old) => writeln!(writer, "{}: {old} -> {value}", name.display()),
+                None => writeln!(writer, "{}: {value}", name.display()),
+            }
+            .ok();
+            watch.value = Some(value);
+        }
+
+        #[cfg(feature = "profile")]
+        if self.0.dump_profile.take() {
+            self.0.profiler.dump(writer);
+        }
+    }
+}
@@ -0,0 +1,19 @@
+use embassy_rp::gpio::{AnyPin, Input, Pull};
+use embassy_time::{Duration, block_for};
+
+/// Reads a bank of DIP switches as a binary number, where the first pin is the least
+/// significant bit.
+///
+/// Each switch should connect its pin to ground when on.
+pub fn read_dip_config<const N: usize>(pins: [AnyPin; N]) -> u32 {
+    let inputs = pins.map(|pin| Input::new(pin, Pull::Up));
+
+    // give the pull-ups time to charge the pins before reading them
+    block_for(Duration::from_micros(10));
+
+    inputs
+        .iter()
+        .enumerate()
+        .filter(|(_, input)| input.is_low())
+        .fold(0, |value, (i, _)| value | (1 << i))
+}
@@ -0,0 +1,349 @@
+#[cfg(feature = "layers")]
+use alloc::vec::Vec;
+#[cfg(feature = "layers")]
+use core::ops::RangeInclusive;
+use core::{convert::Infallible, marker::PhantomData};
+
+#[cfg(not(feature = "framebuffer_rgb666"))]
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::{Pixel, pixelcolor::Rgb888, prelude::*, primitives::Rectangle};
+
+/// Format of the framebuffer's pixels in RAM, which is independent of the format sent to the
+/// display (see `display_rgb565`), since pixels are converted on flush.
+///
+/// Rgb565 by default, which takes 2 bytes per pixel. With the `framebuffer_rgb666` feature, pixels
+/// are stored with the full 18 bits that the display can show, which takes 3 bytes per pixel.
+#[cfg(not(feature = "framebuffer_rgb666"))]
+pub type FramebufferColor = Rgb565;
+#[cfg(feature = "framebuffer_rgb666")]
+pub type FramebufferColor = PackedRgb666;
+
+#[cfg(not(feature = "framebuffer_rgb666"))]
+pub const FRAMEBUFFER_BLACK: FramebufferColor = Rgb565::BLACK;
+#[cfg(feature = "framebuffer_rgb666")]
+pub const FRAMEBUFFER_BLACK: FramebufferColor = PackedRgb666([0; 3]);
+
+/// Rgb666 color packed into 3 bytes, since `Rgb666` itself is stored in 4.
+#[cfg(feature = "framebuffer_rgb666")]
+#[derive(Clone, Copy)]
+pub struct PackedRgb666([u8; 3]);
+
+#[cfg(feature = "framebuffer_rgb666")]
+impl From<Rgb888> for PackedRgb666 {
+    fn from(color: Rgb888) -> Self {
+        Self([color.r(), color.g(), color.b()].map(|c| c & 0xfc))
+    }
+}
+
+#[cfg(feature = "framebuffer_rgb666")]
+impl From<PackedRgb666> for Rgb888 {
+    fn from(PackedRgb666(color): PackedRgb666) -> Self {
+        // fill the low bits so white stays white
+        let [r, g, b] = color.map(|c| c | c >> 6);
+        Rgb888::new(r, g, b)
+    }
+}
+
+/// In-RAM copy of the display for draw commands to render into, so each frame is sent to the
+/// display in one write once it's finished, instead of showing a clear before the redraw.
+///
+/// Pixels are stored as [`FramebufferColor`], and pixels outside of the framebuffer are discarded.
+/// Only the bounding box of the pixels drawn since the last flush is sent, so small updates don't
+/// resend the whole display.
+///
+/// With the `layers` feature, layers added with [`Self::add_layer`] are blended over the pixels
+/// when they're flushed, and draws can be sent to one of them with [`Self::select_layer`].
+pub struct Framebuffer<C> {
+    pixels: &'static mut [FramebufferColor],
+    size: Size,
+    dirty: Option<Rectangle>,
+    color: PhantomData<C>,
+    #[cfg(feature = "layers")]
+    layers: Vec<Layer>,
+    // layer that draws go to: 0 for `pixels`, or `n` for `layers[n - 1]`
+    #[cfg(feature = "layers")]
+    layer: usize,
+}
+
+/// Layer that's blended over the framebuffer with its own alpha. Pixels that haven't been drawn
+/// to since the layer was last cleared are transparent.
+#[cfg(feature = "layers")]
+struct Layer {
+    pixels: &'static mut [FramebufferColor],
+    // one bit per pixel, set where the layer has been drawn to
+    opaque: &'static mut [u32],
+    alpha: u8,
+}
+
+impl<C> Framebuffer<C>
+where
+    C: PixelColor + From<Rgb888>,
+    Rgb888: From<C>,
+{
+    /// Panics if `pixels` is too small for `size`.
+    pub fn new(pixels: &'static mut [FramebufferColor], size: Size) -> Self {
+        let len = size.width as usize * size.height as usize;
+        Self {
+            pixels: &mut pixels[..len],
+            size,
+            dirty: None,
+            color: PhantomData,
+            #[cfg(feature = "layers")]
+            layers: Vec::new(),
+            #[cfg(feature = "layers")]
+            layer: 0,
+        }
+    }
+
+    /// Adds a transparent layer over the existing ones, fully opaque once drawn to. Panics if
+    /// `pixels` or `opaque` (one bit per pixel) are too small for the framebuffer.
+    #[cfg(feature = "layers")]
+    pub fn add_layer(
+        &mut self,
+        pixels: &'static mut [FramebufferColor],
+        opaque: &'static mut [u32],
+    ) {
+        let len = self.pixels.len();
+        let opaque = &mut opaque[..len.div_ceil(32)];
+        opaque.fill(0);
+        self.layers.push(Layer {
+            pixels: &mut pixels[..len],
+            opaque,
+            alpha: u8::MAX,
+        });
+    }
+
+    /// Sends draws to `layer`, where 0 is the framebuffer itself and 1 is the first layer added
+    /// over it. Layers that don't exist are ignored.
+    #[cfg(feature = "layers")]
+    pub fn select_layer(&mut self, layer: usize) {
+        if layer <= self.layers.len() {
+            self.layer = layer;
+        }
+    }
+
+    /// Sets the alpha that `layer` is blended with, from 0 (invisible) to 255 (opaque).
+    #[cfg(feature = "layers")]
+    pub fn set_layer_alpha(&mut self, layer: usize, alpha: u8) {
+        if let Some(layer) = layer.checked_sub(1).and_then(|i| self.layers.get_mut(i))
+            && layer.alpha != alpha
+        {
+            layer.alpha = alpha;
+            // the layer could be anywhere, so the whole frame has to be blended again
+            let bottom_right = Point::new(self.size.width as i32 - 1, self.size.height as i32 - 1);
+            self.mark_dirty(Point::zero(), bottom_right);
+        }
+    }
+
+    /// Writes the part of the frame that changed since the last flush to `target`, and returns
+    /// that area.
+    pub fn flush<T>(&mut self, target: &mut T) -> Result<Option<Rectangle>, T::Error>
+    where
+        T: DrawTarget<Color = C>,
+    {
+        let Some(dirty) = self.dirty.take() else {
+            return Ok(None);
+        };
+        let (x, width) = (dirty.top_left.x as usize, dirty.size.width as usize);
+        let colors = dirty
+            .rows()
+            .flat_map(|y| {
+                let start = y as usize * self.size.width as usize + x;
+                start..start + width
+            })
+            .map(|i| self.composite(i).into());
+        target.fill_contiguous(&dirty, colors)?;
+        Ok(Some(dirty))
+    }
+
+    /// Returns a draw target that blends the pixels drawn to it over the framebuffer's pixels, with
+    /// `alpha` from 0 (invisible) to 255 (opaque).
+    pub fn blended(&mut self, alpha: u8) -> Blended<'_, C> {
+        Blended {
+            framebuffer: self,
+            alpha,
+        }
+    }
+
+    /// Returns the color of a pixel as it's sent to the display, or `None` if it's outside of the
+    /// framebuffer.
+    pub fn get(&self, point: Point) -> Option<Rgb888> {
+        self.index(point).map(|i| self.composite(i))
+    }
+
+    /// Returns the color of pixel `i` with any layers blended over it.
+    fn composite(&self, i: usize) -> Rgb888 {
+        let color = self.pixels[i].into();
+        #[cfg(feature = "layers")]
+        let color = self
+            .layers
+            .iter()
+            .filter(|layer| layer.opaque[i / 32] & (1 << (i % 32)) != 0)
+            .fold(color, |color, layer| {
+                blend(color, layer.pixels[i].into(), layer.alpha)
+            });
+        color
+    }
+
+    /// Returns the pixels of the layer that draws go to.
+    fn layer_pixels(&mut self) -> &mut [FramebufferColor] {
+        #[cfg(feature = "layers")]
+        if self.layer > 0 {
+            return &mut self.layers[self.layer - 1].pixels[..];
+        }
+        &mut self.pixels[..]
+    }
+
+    /// Marks `pixels` of the layer that draws go to as drawn, so they're blended on flush.
+    #[cfg(feature = "layers")]
+    fn mark_opaque(&mut self, pixels: RangeInclusive<usize>) {
+        if self.layer > 0 {
+            let opaque = &mut self.layers[self.layer - 1].opaque;
+            for i in pixels {
+                opaque[i / 32] |= 1 << (i % 32);
+            }
+        }
+    }
+
+    /// Sets each pixel to the result of `color` with the pixel's current color and the drawn color.
+    fn draw_with<I, F>(&mut self, pixels: I, mut color: F)
+    where
+        I: IntoIterator<Item = Pixel<C>>,
+        F: FnMut(FramebufferColor, C) -> FramebufferColor,
+    {
+        let mut drawn: Option<(Point, Point)> = None;
+        for Pixel(point, new) in pixels {
+            if let Some(i) = self.index(point) {
+                let pixels = self.layer_pixels();
+                pixels[i] = color(pixels[i], new);
+                #[cfg(feature = "layers")]
+                self.mark_opaque(i..=i);
+                drawn = Some(match drawn {
+                    Some((min, max)) => (min.component_min(point), max.component_max(point)),
+                    None => (point, point),
+                });
+            }
+        }
+        if let Some((min, max)) = drawn {
+            self.mark_dirty(min, max);
+        }
+    }
+
+    /// Grows the dirty area to include the rectangle from `top_left` to `bottom_right`.
+    fn mark_dirty(&mut self, top_left: Point, bottom_right: Point) {
+        let (top_left, bottom_right) = match self
+            .dirty
+            .and_then(|dirty| Some((dirty.top_left, dirty.bottom_right()?)))
+        {
+            Some((dirty_top_left, dirty_bottom_right)) => (
+                top_left.component_min(dirty_top_left),
+                bottom_right.component_max(dirty_bottom_right),
+            ),
+            None => (top_left, bottom_right),
+        };
+        self.dirty = Some(Rectangle::with_corners(top_left, bottom_right));
+    }
+
+    fn index(&self, point: Point) -> Option<usize> {
+        let (x, y) = (u32::try_from(point.x).ok()?, u32::try_from(point.y).ok()?);
+        (x < self.size.width && y < self.size.height).then(|| (y * self.size.width + x) as usize)
+    }
+}
+
+impl<C> OriginDimensions for Framebuffer<C> {
+    fn size(&self) -> Size {
+        self.size
+    }
+}
+
+impl<C> DrawTarget for Framebuffer<C>
+where
+    C: PixelColor + From<Rgb888>,
+    Rgb888: From<C>,
+{
+    type Color = C;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.draw_with(pixels, |_, color| Rgb888::from(color).into());
+        Ok(())
+    }
+
+    // clears are the common case, so fill whole rows at once
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let area = area.intersection(&self.bounding_box());
+        let Some(bottom_right) = area.bottom_right() else {
+            return Ok(());
+        };
+        for y in area.top_left.y..=bottom_right.y {
+            let start = self.index(Point::new(area.top_left.x, y)).unwrap();
+            let end = self.index(Point::new(bottom_right.x, y)).unwrap();
+            self.layer_pixels()[start..=end].fill(Rgb888::from(color).into());
+            #[cfg(feature = "layers")]
+            self.mark_opaque(start..=end);
+        }
+        // for a clear, this marks the whole framebuffer as dirty
+        self.mark_dirty(area.top_left, bottom_right);
+        Ok(())
+    }
+
+    // clearing a layer makes it transparent again, so the ones below show through
+    #[cfg(feature = "layers")]
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.fill_solid(&self.bounding_box(), color)?;
+        if self.layer > 0 {
+            self.layers[self.layer - 1].opaque.fill(0);
+        }
+        Ok(())
+    }
+}
+
+/// Draw target that blends pixels over a [`Framebuffer`], from [`Framebuffer::blended`].
+///
+/// Each pixel is blended separately, so pixels that a drawable draws more than once (eg. where
+/// the lines of a wide outline overlap) end up more opaque.
+pub struct Blended<'a, C> {
+    framebuffer: &'a mut Framebuffer<C>,
+    alpha: u8,
+}
+
+impl<C> OriginDimensions for Blended<'_, C> {
+    fn size(&self) -> Size {
+        self.framebuffer.size
+    }
+}
+
+impl<C> DrawTarget for Blended<'_, C>
+where
+    C: PixelColor + From<Rgb888>,
+    Rgb888: From<C>,
+{
+    type Color = C;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let alpha = self.alpha;
+        self.framebuffer.draw_with(pixels, |old, new| {
+            blend(Rgb888::from(old), Rgb888::from(new), alpha).into()
+        });
+        Ok(())
+    }
+}
+
+fn blend(old: Rgb888, new: Rgb888, alpha: u8) -> Rgb888 {
+    let mix = |old: u8, new: u8| {
+        let (old, new, alpha) = (old as u16, new as u16, alpha as u16);
+        ((new * alpha + old * (255 - alpha) + 127) / 255) as u8
+    };
+    Rgb888::new(
+        mix(old.r(), new.r()),
+        mix(old.g(), new.g()),
+        mix(old.b(), new.b()),
+    )
+}
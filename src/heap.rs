@@ -0,0 +1,135 @@
+use alloc::rc::Rc;
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    cell::Cell,
+    fmt::Write,
+    mem::MaybeUninit,
+};
+
+use embedded_alloc::TlsfHeap;
+use portable_atomic::{AtomicUsize, Ordering};
+
+use crate::{HEAP_SIZE, buildings::SerialWriter, platform::reboot_to_program};
+
+const DEFAULT_THRESHOLD: usize = 4 * 1024;
+
+#[global_allocator]
+static HEAP: TrackedHeap = TrackedHeap {
+    heap: TlsfHeap::empty(),
+    used: AtomicUsize::new(0),
+};
+
+/// Must be called once, before anything is allocated.
+///
+/// The size is set by the build script, from the selected program's `# heap:` comment.
+pub fn init() {
+    static mut HEAP_MEM: [MaybeUninit<u8>; HEAP_SIZE] = [MaybeUninit::uninit(); HEAP_SIZE];
+    unsafe { HEAP.heap.init(&raw mut HEAP_MEM as usize, HEAP_SIZE) };
+}
+
+/// Approximate number of free bytes on the heap. This doesn't include the allocator's overhead
+/// or fragmentation, so an allocation can fail before it reaches 0.
+pub fn free() -> usize {
+    HEAP_SIZE.saturating_sub(HEAP.used.load(Ordering::Relaxed))
+}
+
+/// Heap that keeps track of how many bytes are allocated.
+struct TrackedHeap {
+    heap: TlsfHeap,
+    used: AtomicUsize,
+}
+
+unsafe impl GlobalAlloc for TrackedHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { self.heap.alloc(layout) };
+        if !ptr.is_null() {
+            self.used.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { self.heap.dealloc(ptr, layout) };
+        self.used.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = unsafe { self.heap.realloc(ptr, layout, new_size) };
+        if !new_ptr.is_null() {
+            self.used.fetch_add(new_size, Ordering::Relaxed);
+            self.used.fetch_sub(layout.size(), Ordering::Relaxed);
+        }
+        new_ptr
+    }
+}
+
+/// What to do when the heap is nearly full, set with `control config tasks @heapAction`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Nothing,
+    Warn,
+    // gives the serial and UART tasks a chance to send queued text, which is usually what fills
+    // the heap
+    WarnAndPause,
+    Reset,
+}
+
+struct State {
+    program: usize,
+    threshold: Cell<usize>,
+    action: Cell<Action>,
+    warned: Cell<bool>,
+}
+
+/// Watches the heap's free space from the main loop, so a program that's running out of memory
+/// can be reported before an allocation fails.
+#[derive(Clone)]
+pub struct HeapMonitor(Rc<State>);
+
+impl HeapMonitor {
+    /// `program` is the index in `PROGRAMS` to restart with the reset action.
+    pub fn new(program: usize) -> Self {
+        Self(Rc::new(State {
+            program,
+            threshold: Cell::new(DEFAULT_THRESHOLD),
+            action: Cell::new(Action::Warn),
+            warned: Cell::new(false),
+        }))
+    }
+
+    pub fn set_threshold(&self, bytes: f64) {
+        self.0.threshold.set(bytes.max(0.) as usize);
+    }
+
+    /// 0 does nothing, 1 warns over serial, 2 also pauses the processor until there's enough
+    /// memory again, and 3 restarts the program.
+    pub fn set_action(&self, action: f64) {
+        self.0.action.set(match action as u32 {
+            0 => Action::Nothing,
+            1 => Action::Warn,
+            2 => Action::WarnAndPause,
+            _ => Action::Reset,
+        });
+    }
+
+    /// Checks the free space and acts on it, and returns true if the processor should be paused.
+    pub fn check(&self, writer: &mut SerialWriter) -> bool {
+        let free = free();
+        if free >= self.0.threshold.get() {
+            self.0.warned.set(false);
+            return false;
+        }
+
+        match self.0.action.get() {
+            Action::Nothing => false,
+            action @ (Action::Warn | Action::WarnAndPause) => {
+                // once each time it drops below the threshold
+                if !self.0.warned.replace(true) {
+                    writeln!(writer, "warning: heap nearly full ({free} bytes free)").ok();
+                }
+                action == Action::WarnAndPause
+            }
+            Action::Reset => reboot_to_program(self.0.program),
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use alloc::rc::Rc;
+use core::cell::Cell;
+
+/// Shared count of the instructions the processor has executed in the current tick.
+#[derive(Clone, Default)]
+pub struct InstructionCounter(Rc<Cell<u32>>);
+
+impl InstructionCounter {
+    pub fn get(&self) -> u32 {
+        self.0.get()
+    }
+
+    pub fn increment(&self) {
+        self.0.set(self.0.get().saturating_add(1));
+    }
+
+    pub fn reset(&self) {
+        self.0.set(0);
+    }
+}
@@ -0,0 +1,49 @@
+use alloc::string::String;
+use core::fmt;
+
+use mindy::types::ProcessorLinkConfig;
+
+#[derive(Debug)]
+pub enum LinkError {
+    DuplicateName(String),
+    NoBuilding { name: String, x: i32, y: i32 },
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuplicateName(name) => write!(f, "duplicate link name: {name}"),
+            Self::NoBuilding { name, x, y } => {
+                write!(
+                    f,
+                    "link {name} points at ({x}, {y}), but there's no building there"
+                )
+            }
+        }
+    }
+}
+
+/// Checks that the processor's link names are unique, and that each link points at a building.
+///
+/// `positions` are the `(x, y)` positions of the linkable buildings. The processor is assumed to be
+/// at the origin, so link coordinates are absolute.
+pub fn check_links(
+    links: &[ProcessorLinkConfig],
+    positions: &[(i32, i32)],
+) -> Result<(), LinkError> {
+    for (i, link) in links.iter().enumerate() {
+        if links[..i].iter().any(|other| other.name == link.name) {
+            return Err(LinkError::DuplicateName(link.name.clone()));
+        }
+
+        let (x, y) = (link.x as i32, link.y as i32);
+        if !positions.contains(&(x, y)) {
+            return Err(LinkError::NoBuilding {
+                name: link.name.clone(),
+                x,
+                y,
+            });
+        }
+    }
+    Ok(())
+}
@@ -3,12 +3,14 @@
 
 extern crate alloc;
 
-use alloc::boxed::Box;
-use core::{cell::RefCell, mem::MaybeUninit};
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::{cell::RefCell, iter};
 
 use embassy_embedded_hal::shared_bus::blocking::spi::SpiDevice;
 use embassy_executor::Spawner;
 use embassy_futures::yield_now;
+#[cfg(any(feature = "backlight_pwm", feature = "rgb_led"))]
+use embassy_rp::pwm;
 use embassy_rp::{
     bind_interrupts,
     gpio::{self, Pin},
@@ -18,83 +20,189 @@ use embassy_rp::{
     usb,
 };
 use embassy_sync::blocking_mutex::{Mutex, raw::NoopRawMutex};
-use embassy_time::{Delay, Instant, Timer};
+use embassy_time::{Delay, Duration, Instant, Timer};
 use embassy_usb::{
     UsbDevice,
     class::cdc_acm::{self, CdcAcmClass},
 };
-use embedded_alloc::TlsfHeap as Heap;
+use embedded_graphics::prelude::Dimensions;
 use embedded_io_async::Write;
 use mindy::{
     parser::deserialize_ast,
     types::{PackedPoint2, ProcessorLinkConfig},
-    vm::{
-        Building, EmbeddedDisplayData, LVar, LogicVMBuilder, ProcessorBuilder,
-        instructions::Instruction,
-    },
+    vm::{Building, LVar, LogicVMBuilder, ProcessorBuilder, instructions::Instruction},
 };
 use mipidsi::{
     interface::SpiInterface,
+    models::Model,
     options::{ColorInversion, Orientation, Rotation},
 };
 use panic_persist::get_panic_message_bytes;
-use widestring::u16str;
-
+use widestring::{U16String, u16str};
+
+#[cfg(feature = "backlight_pwm")]
+use self::backlight::Backlight;
+#[cfg(feature = "bounds_check")]
+use self::bounds_check::BoundsCheckedDrawTarget;
+#[cfg(feature = "duty_cycle")]
+use self::buildings::DutyCycleInput;
+#[cfg(feature = "ir")]
+use self::buildings::IrData;
+#[cfg(feature = "joystick")]
+use self::buildings::JoystickData;
+#[cfg(feature = "neopixel")]
+use self::buildings::NeopixelData;
+#[cfg(feature = "one_wire")]
+use self::buildings::OneWireData;
+#[cfg(feature = "pulse_in")]
+use self::buildings::PulseInput;
+#[cfg(feature = "retained")]
+use self::buildings::RetainedData;
+#[cfg(feature = "selector")]
+use self::buildings::SelectorData;
+#[cfg(feature = "temp_sensor")]
+use self::buildings::TempSensorData;
+#[cfg(feature = "ultrasonic")]
+use self::buildings::UltrasonicData;
+#[cfg(feature = "dht")]
+use self::buildings::{DhtData, DhtModel};
+#[cfg(feature = "dip_config")]
+use self::dip_config::read_dip_config;
+#[cfg(feature = "framebuffer")]
+use self::framebuffer::{FRAMEBUFFER_BLACK, FramebufferColor};
+#[cfg(feature = "ssd1306")]
+use self::oled::Oled;
+#[cfg(feature = "rgb_led")]
+use self::rgb_led::{FirmwareState, RgbLed};
 use self::{
-    buildings::{GpioData, SerialData, UartData, gpio_data_pin},
+    buildings::{
+        Bridge, ConfigOption, DisplayData, FlashData, FramesData, GpioData, ImageBankData,
+        ProcessorClock, ResetReason, SerialData, SharedBuilding, Stat, TaskStatsData, TickData,
+        UartData, auto_flush_lines, gpio_data_pin,
+    },
+    counting_spi::CountingSpiDevice,
+    debug_commands::DebugCommands,
+    heap::HeapMonitor,
+    instruction_counter::InstructionCounter,
+    links::check_links,
+    pixel_stream::PixelStream,
+    platform::{reboot_to_bootsel, reset_reason, take_requested_program},
+    shared_draw_target::SharedDrawTarget,
     st7789vw::ST7789VW,
+    status_led::StatusLed,
+    task_stats::{TaskCounter, count_polls},
 };
 
+#[cfg(feature = "backlight_pwm")]
+mod backlight;
+#[cfg(feature = "bounds_check")]
+mod bounds_check;
 mod buildings;
+mod counting_spi;
 mod custom_content;
+mod debug_commands;
+#[cfg(feature = "dip_config")]
+mod dip_config;
+#[cfg(feature = "framebuffer")]
+mod framebuffer;
+mod heap;
+mod instruction_counter;
+mod links;
+#[cfg(feature = "ssd1306")]
+mod oled;
+mod pixel_stream;
+mod platform;
+#[cfg(feature = "profile")]
+mod profiler;
+#[cfg(feature = "proportional_text")]
+mod proportional_text;
+#[cfg(feature = "rgb_led")]
+mod rgb_led;
+mod rotated;
+mod shared_draw_target;
+#[cfg(feature = "sprites")]
+mod sprites;
 mod st7789vw;
-
-macro_rules! include_ast {
-    ($name:expr) => {
-        #[cfg(feature = $name)]
-        const AST_BYTES: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/mlog/", $name, ".bin"));
-        #[cfg(feature = $name)]
-        const PROGRAM_NAME: &str = $name;
-    };
+mod status_led;
+mod task_stats;
+
+// defines PROGRAMS for every program, and DEFAULT_PROGRAM for the one selected by Cargo features
+include!(concat!(env!("OUT_DIR"), "/program.rs"));
+
+/// A program embedded by the build script.
+struct Program {
+    name: &'static str,
+    /// Global constant holding this program's index, for `@runProgram`.
+    global: &'static str,
+    ast_bytes: &'static [u8],
+    /// Initial display rotation, from the program's `# orientation:` comment.
+    rotation: Rotation,
+    /// Heap size in bytes, from the program's `# heap:` comment.
+    heap_size: usize,
 }
 
-include_ast!("blink");
-include_ast!("button_matrix");
-include_ast!("draw");
-include_ast!("mandelbrot");
-include_ast!("print");
-include_ast!("print_usb");
-
-const HEAP_SIZE: usize = 64 * 1024;
-
-#[global_allocator]
-static HEAP: Heap = Heap::empty();
+impl Program {
+    /// Returns the program at `index` if it can be switched to at runtime, which needs it to fit
+    /// in the heap allocated for the program selected at build time.
+    fn runnable(index: usize) -> Option<&'static Self> {
+        PROGRAMS
+            .get(index)
+            .filter(|program| program.heap_size <= HEAP_SIZE)
+    }
+}
 
 bind_interrupts!(struct Irqs {
     UART0_IRQ => uart::BufferedInterruptHandler<UART0>;
     USBCTRL_IRQ => usb::InterruptHandler<USB>;
 });
 
+#[cfg(any(feature = "ir", feature = "pulse_in"))]
+bind_interrupts!(struct PioIrqs {
+    PIO0_IRQ_0 => embassy_rp::pio::InterruptHandler<embassy_rp::peripherals::PIO0>;
+});
+
+#[cfg(feature = "neopixel")]
+bind_interrupts!(struct Pio1Irqs {
+    PIO1_IRQ_0 => embassy_rp::pio::InterruptHandler<embassy_rp::peripherals::PIO1>;
+});
+
 const MAX_USB_PACKET_SIZE: usize = 64;
 const UART_BUFFER_SIZE: usize = 400;
 
+const PROCESSOR_IPT: f64 = 100.;
+
+// number of LEDs on the NeoPixel strip, which each use 3 bytes of RAM
+#[cfg(feature = "neopixel")]
+const NEOPIXEL_COUNT: usize = 16;
+const IDLE_SLEEP: Duration = Duration::from_millis(1);
+
+// set in .cargo/config.toml, in the display's native (unrotated) orientation
+const DISPLAY_OFFSET_X: u16 = parse_env_u16(env!("DISPLAY_OFFSET_X"));
+const DISPLAY_OFFSET_Y: u16 = parse_env_u16(env!("DISPLAY_OFFSET_Y"));
+// pixel data is sent to the display in chunks of this many bytes
+const DISPLAY_SPI_CHUNK_SIZE: usize = parse_env_u16(env!("DISPLAY_SPI_CHUNK_SIZE")) as usize;
+// processor ticks between each time the other tasks and peripherals get a turn
+const VM_TICKS_PER_LOOP: u16 = parse_env_u16(env!("VM_TICKS_PER_LOOP"));
+
 #[embassy_executor::task]
-async fn usb_task(mut usb: UsbDevice<'static, usb::Driver<'static, USB>>) {
-    usb.run().await;
+async fn usb_task(mut usb: UsbDevice<'static, usb::Driver<'static, USB>>, counter: TaskCounter) {
+    count_polls(usb.run(), counter).await;
 }
 
 #[embassy_executor::main]
 async fn main(spawner: Spawner) {
-    // init heap
-    {
-        static mut HEAP_MEM: [MaybeUninit<u8>; HEAP_SIZE] = [MaybeUninit::uninit(); HEAP_SIZE];
-        unsafe { HEAP.init(&raw mut HEAP_MEM as usize, HEAP_SIZE) };
-    }
+    heap::init();
 
     // init peripherals
 
     let p = embassy_rp::init(Default::default());
 
+    // a program requested with @runProgram overrides the one selected by Cargo features
+    let program_index = take_requested_program()
+        .filter(|&index| Program::runnable(index).is_some())
+        .unwrap_or(DEFAULT_PROGRAM);
+    let program = &PROGRAMS[program_index];
+
     let uart_config = uart::Config::default();
     let mut uart0 = BufferedUart::new(
         p.UART0,
@@ -106,12 +214,23 @@ async fn main(spawner: Spawner) {
         uart_config,
     );
 
+    // external status LED, with red on GP7, green on GP8 and blue on GP9
+    #[cfg(feature = "rgb_led")]
+    let mut rgb_led = {
+        let (_, red) = pwm::Pwm::new_output_b(p.PWM_SLICE3, p.PIN_7, Default::default()).split();
+        let (green, blue) =
+            pwm::Pwm::new_output_ab(p.PWM_SLICE4, p.PIN_8, p.PIN_9, Default::default()).split();
+        RgbLed::new(red.unwrap(), green.unwrap(), blue.unwrap())
+    };
+
     // as soon as the UART is up, check if we panicked on the previous boot
     if let Some(msg) = get_panic_message_bytes() {
+        #[cfg(feature = "rgb_led")]
+        rgb_led.set_state(FirmwareState::PanicRecovered);
         uart0.write_all(msg).await.unwrap();
         uart0.flush().await.unwrap();
         Timer::after_secs(1).await;
-        cortex_m::peripheral::SCB::sys_reset();
+        recover_from_panic();
     }
 
     // set up USB
@@ -122,7 +241,7 @@ async fn main(spawner: Spawner) {
     let mut usb_config = embassy_usb::Config::new(0x1209, 0x0001);
     usb_config.manufacturer = Some("object-Object");
     usb_config.product = Some("mlog-pico");
-    usb_config.serial_number = Some(PROGRAM_NAME);
+    usb_config.serial_number = Some(program.name);
 
     let mut usb_builder = embassy_usb::Builder::new(
         usb_driver,
@@ -140,21 +259,32 @@ async fn main(spawner: Spawner) {
     );
 
     let usb = usb_builder.build();
-    spawner.must_spawn(usb_task(usb));
+    let usb_task_counter = TaskCounter::default();
+    spawner.must_spawn(usb_task(usb, usb_task_counter.clone()));
 
     serial_class.wait_connection().await;
 
     // https://github.com/embassy-rs/embassy/blob/ac46e28c4b4f025279d8974adfb6120c6740e44e/examples/rp/src/bin/spi_display.rs
     let mut display_config = spi::Config::default();
     display_config.frequency = 64_000_000;
-    display_config.phase = spi::Phase::CaptureOnSecondTransition;
-    display_config.polarity = spi::Polarity::IdleHigh;
+    #[cfg(not(feature = "display_spi_mode0"))]
+    {
+        display_config.phase = spi::Phase::CaptureOnSecondTransition;
+        display_config.polarity = spi::Polarity::IdleHigh;
+    }
+    #[cfg(feature = "display_spi_mode0")]
+    {
+        display_config.phase = spi::Phase::CaptureOnFirstTransition;
+        display_config.polarity = spi::Polarity::IdleLow;
+    }
 
     // st7789v pins
     let din = p.PIN_11;
     let clk = p.PIN_14;
     let cs = p.PIN_13;
     let dc = p.PIN_10;
+    // left floating in software reset mode, in case it's shared with something else
+    #[cfg(not(feature = "display_soft_reset"))]
     let rst = p.PIN_12;
     let bl = p.PIN_15;
 
@@ -175,143 +305,579 @@ async fn main(spawner: Spawner) {
         display_config,
     )));
 
-    let display_spi = SpiDevice::new(leak(spi_bus), gpio::Output::new(cs, gpio::Level::High));
+    let (display_spi, display_spi_counter) = CountingSpiDevice::new(SpiDevice::new(
+        leak(spi_bus),
+        gpio::Output::new(cs, gpio::Level::High),
+    ));
 
     let di = SpiInterface::new(
         display_spi,
         gpio::Output::new(dc, gpio::Level::Low),
-        leak([0; 512]),
+        leak([0; DISPLAY_SPI_CHUNK_SIZE]),
     );
 
     // disable backlight while initializing display so it doesn't show whatever was drawn on the previous boot
-    let bl_pin = bl.pin();
-    let mut bl = gpio::Flex::new(bl);
-    bl.set_level(gpio::Level::Low);
-    bl.set_as_output();
-
-    let display = mipidsi::Builder::new(ST7789VW, di)
-        .reset_pin(gpio::Output::new(rst, gpio::Level::Low))
-        .orientation(Orientation::new().rotate(Rotation::Deg90))
+    #[cfg(feature = "backlight_pwm")]
+    let mut backlight = Backlight::new(pwm::Pwm::new_output_b(
+        p.PWM_SLICE7,
+        bl,
+        pwm::Config::default(),
+    ));
+    #[cfg(not(feature = "backlight_pwm"))]
+    let (bl_pin, mut bl) = {
+        let bl_pin = bl.pin();
+        let mut bl = gpio::Flex::new(bl);
+        bl.set_level(gpio::Level::Low);
+        bl.set_as_output();
+        (bl_pin, bl)
+    };
+
+    let builder = mipidsi::Builder::new(ST7789VW, di);
+    // without a reset pin, mipidsi sends a software reset command instead
+    #[cfg(not(feature = "display_soft_reset"))]
+    let builder = builder.reset_pin(gpio::Output::new(rst, gpio::Level::Low));
+
+    let display = builder
+        .orientation(Orientation::new().rotate(program.rotation))
+        .display_offset(DISPLAY_OFFSET_X, DISPLAY_OFFSET_Y)
+        .display_size(
+            ST7789VW::FRAMEBUFFER_SIZE.0 - DISPLAY_OFFSET_X,
+            ST7789VW::FRAMEBUFFER_SIZE.1 - DISPLAY_OFFSET_Y,
+        )
         // inverted apparently means normal for this display (???)
         .invert_colors(ColorInversion::Inverted)
         .init(&mut Delay)
         .unwrap();
 
-    let display_data = EmbeddedDisplayData::new(display, true).unwrap();
+    #[cfg(feature = "bounds_check")]
+    let (display, out_of_bounds_counter) = BoundsCheckedDrawTarget::new(display);
+
+    // shared so that pixel streams can draw to the display without going through mlog
+    let mut display = SharedDrawTarget::new(display);
+
+    let instruction_counter = InstructionCounter::default();
+
+    let display_data = DisplayData::new(display.clone(), true)
+        .unwrap()
+        .with_spi_counter(display_spi_counter)
+        .with_instruction_counter(instruction_counter.clone(), PROCESSOR_IPT);
+
+    let frames_data = FramesData::default();
+    let image_bank_data = ImageBankData::default();
+    let display_data = display_data
+        .with_frame_counter(frames_data.clone())
+        .with_image_bank(image_bank_data.clone());
+
+    #[cfg(feature = "bounds_check")]
+    let display_data = display_data.with_out_of_bounds_counter(out_of_bounds_counter);
+
+    #[cfg(feature = "framebuffer")]
+    let display_data = {
+        const PIXELS: usize =
+            ST7789VW::FRAMEBUFFER_SIZE.0 as usize * ST7789VW::FRAMEBUFFER_SIZE.1 as usize;
+        // too big for the heap, and only borrowed once here
+        static mut FRAMEBUFFER: [FramebufferColor; PIXELS] = [FRAMEBUFFER_BLACK; PIXELS];
+        let display_data = display_data.with_framebuffer(unsafe { &mut *(&raw mut FRAMEBUFFER) });
+
+        // with one bit per pixel for where it's been drawn to
+        #[cfg(feature = "layers")]
+        let display_data = {
+            const OPAQUE_WORDS: usize = PIXELS.div_ceil(32);
+            static mut LAYER: [FramebufferColor; PIXELS] = [FRAMEBUFFER_BLACK; PIXELS];
+            static mut LAYER_OPAQUE: [u32; OPAQUE_WORDS] = [0; OPAQUE_WORDS];
+            display_data.with_layer(unsafe { &mut *(&raw mut LAYER) }, unsafe {
+                &mut *(&raw mut LAYER_OPAQUE)
+            })
+        };
+
+        display_data
+    };
 
+    #[cfg(feature = "backlight_pwm")]
+    let display_data = {
+        backlight.set_brightness(1.);
+        display_data.with_backlight(backlight)
+    };
+    #[cfg(not(feature = "backlight_pwm"))]
     bl.set_level(gpio::Level::High);
 
-    let (uart0_data, mut uart0_tick) = UartData::new(uart0);
+    // second display, on I2C0 with SDA on GP16 and SCL on GP17
+    #[cfg(feature = "ssd1306")]
+    let oled_data = {
+        let mut i2c_config = embassy_rp::i2c::Config::default();
+        i2c_config.frequency = 400_000;
+        let i2c = embassy_rp::i2c::I2c::new_blocking(p.I2C0, p.PIN_17, p.PIN_16, i2c_config);
+        DisplayData::new(Oled::new(i2c).unwrap(), true)
+            .unwrap()
+            .with_flush(Oled::flush)
+    };
+
+    let bridge = Bridge::default();
+    let (uart0_data, mut uart0_tick) = UartData::new(uart0, bridge.clone());
+
+    let pixel_stream = PixelStream::new(display.bounding_box());
+
+    let debug_commands = DebugCommands::default();
+    #[cfg(feature = "profile")]
+    let profiler = debug_commands.profiler();
 
-    let (serial_data, serial_task, mut serial_tick) = SerialData::new(serial_class);
+    let serial_task_counter = TaskCounter::default();
+
+    let (serial_data, serial_task, mut serial_tick) = SerialData::new(
+        serial_class,
+        pixel_stream.clone(),
+        debug_commands.clone(),
+        serial_task_counter.clone(),
+        bridge,
+    );
     spawner.must_spawn(serial_task);
 
+    let main_loop_counter = TaskCounter::default();
+    let processor_clock = ProcessorClock::default();
+    let (task_stats_data, mut task_stats_tick) = TaskStatsData::new(vec![
+        usb_task_counter,
+        serial_task_counter,
+        main_loop_counter.clone(),
+    ]);
+    let heap_monitor = HeapMonitor::new(program_index);
+    let mut heap_writer = serial_data.writer();
+    let task_stats_data = task_stats_data.with_heap_monitor(heap_monitor.clone());
+
+    let draw_errors = display_data.draw_error_counter();
+
+    // shared so that the instruction hook can draw in immediate mode
+    let display_data = SharedBuilding::new(
+        display_data
+            .with_draw_log(serial_data.writer())
+            .with_text_mirror(serial_data.writer()),
+    );
+
+    // turns the backlight back on after strobes
+    #[cfg(feature = "backlight_pwm")]
+    let backlight_display = display_data.clone();
+
+    let gpio_data = GpioData::new([
+        #[cfg(not(feature = "ultrasonic"))]
+        gpio_data_pin!(p.PIN_2),
+        #[cfg(not(any(feature = "ultrasonic", feature = "pulse_in")))]
+        gpio_data_pin!(p.PIN_3),
+        #[cfg(not(feature = "dht"))]
+        gpio_data_pin!(p.PIN_4),
+        #[cfg(not(feature = "duty_cycle"))]
+        gpio_data_pin!(p.PIN_5),
+        #[cfg(not(feature = "one_wire"))]
+        gpio_data_pin!(p.PIN_6),
+        #[cfg(not(feature = "rgb_led"))]
+        gpio_data_pin!(p.PIN_7),
+        #[cfg(not(feature = "rgb_led"))]
+        gpio_data_pin!(p.PIN_8),
+        #[cfg(not(feature = "rgb_led"))]
+        gpio_data_pin!(p.PIN_9),
+        // the display's backlight, which mlog can turn off to save power
+        #[cfg(not(feature = "backlight_pwm"))]
+        (bl_pin as usize, bl),
+        #[cfg(not(feature = "ssd1306"))]
+        gpio_data_pin!(p.PIN_16),
+        #[cfg(not(feature = "ssd1306"))]
+        gpio_data_pin!(p.PIN_17),
+        #[cfg(not(any(feature = "dip_config", feature = "selector")))]
+        gpio_data_pin!(p.PIN_18),
+        #[cfg(not(any(feature = "dip_config", feature = "selector")))]
+        gpio_data_pin!(p.PIN_19),
+        #[cfg(not(any(feature = "dip_config", feature = "selector")))]
+        gpio_data_pin!(p.PIN_20),
+        #[cfg(not(any(feature = "dip_config", feature = "selector")))]
+        gpio_data_pin!(p.PIN_21),
+        #[cfg(not(any(feature = "joystick", feature = "neopixel")))]
+        gpio_data_pin!(p.PIN_22),
+        #[cfg(not(any(feature = "joystick", feature = "adc")))]
+        gpio_data_pin!(p.PIN_26),
+        #[cfg(not(any(feature = "joystick", feature = "adc")))]
+        gpio_data_pin!(p.PIN_27),
+        #[cfg(not(any(feature = "adc", feature = "ir")))]
+        gpio_data_pin!(p.PIN_28),
+    ]);
+
+    // slices owned through embassy_rp::pwm, whose pins can't output PWM from gpio
+    let gpio_data = gpio_data.with_reserved_pwm_slices([
+        #[cfg(feature = "rgb_led")]
+        3,
+        #[cfg(feature = "rgb_led")]
+        4,
+        #[cfg(feature = "backlight_pwm")]
+        7,
+    ]);
+
+    let status_led = StatusLed::new(gpio::Flex::new(p.PIN_25));
+    let gpio_data = gpio_data.with_status_led(25, status_led.clone());
+
+    #[cfg(feature = "duty_cycle")]
+    let gpio_data = {
+        let (pin, task) = DutyCycleInput::new(gpio::Input::new(p.PIN_5, gpio::Pull::None));
+        spawner.must_spawn(task);
+        gpio_data.with_duty_cycle_pins([(5, pin)])
+    };
+
+    // shared by the IR LED and pulse measurement, which each use a state machine
+    #[cfg(any(feature = "ir", feature = "pulse_in"))]
+    let mut pio0 = embassy_rp::pio::Pio::new(p.PIO0, PioIrqs);
+
+    #[cfg(feature = "pulse_in")]
+    let gpio_data = {
+        let pin = pio0.common.make_pio_pin(p.PIN_3);
+        let (pin, task) = PulseInput::new(&mut pio0.common, pio0.sm1, pin);
+        spawner.must_spawn(task);
+        gpio_data.with_pulse_pins([(3, pin)])
+    };
+
+    #[cfg(feature = "adc")]
+    let gpio_data = {
+        use embassy_rp::adc::{Adc, Channel};
+        gpio_data.with_analog_pins(
+            Adc::new_blocking(p.ADC, Default::default()),
+            [
+                (26, Channel::new_pin(p.PIN_26, gpio::Pull::None)),
+                (27, Channel::new_pin(p.PIN_27, gpio::Pull::None)),
+                (28, Channel::new_pin(p.PIN_28, gpio::Pull::None)),
+            ],
+        )
+    };
+
+    // NeoPixel strip data on GP22
+    #[cfg(feature = "neopixel")]
+    let neopixel_data = {
+        let mut pio = embassy_rp::pio::Pio::new(p.PIO1, Pio1Irqs);
+        let pin = pio.common.make_pio_pin(p.PIN_22);
+        NeopixelData::<NEOPIXEL_COUNT>::new(pio, pin)
+    };
+
+    // IR LED on GP28
+    #[cfg(feature = "ir")]
+    let ir_data = {
+        let pin = pio0.common.make_pio_pin(p.PIN_28);
+        IrData::new(&mut pio0.common, pio0.sm0, pin)
+    };
+
     // build VM
 
+    let links = [
+        ProcessorLinkConfig {
+            name: "gpio".into(),
+            x: 1,
+            y: 0,
+        },
+        ProcessorLinkConfig {
+            name: "uart0".into(),
+            x: 2,
+            y: 0,
+        },
+        ProcessorLinkConfig {
+            name: "serial".into(),
+            x: 3,
+            y: 0,
+        },
+        ProcessorLinkConfig {
+            name: "display1".into(),
+            x: 4,
+            y: 0,
+        },
+        #[cfg(feature = "ultrasonic")]
+        ProcessorLinkConfig {
+            name: "ultrasonic".into(),
+            x: 5,
+            y: 0,
+        },
+        #[cfg(feature = "dht")]
+        ProcessorLinkConfig {
+            name: "dht".into(),
+            x: 6,
+            y: 0,
+        },
+        ProcessorLinkConfig {
+            name: "tasks".into(),
+            x: 7,
+            y: 0,
+        },
+        #[cfg(feature = "one_wire")]
+        ProcessorLinkConfig {
+            name: "onewire".into(),
+            x: 8,
+            y: 0,
+        },
+        ProcessorLinkConfig {
+            name: "flash".into(),
+            x: 9,
+            y: 0,
+        },
+        #[cfg(feature = "retained")]
+        ProcessorLinkConfig {
+            name: "retained".into(),
+            x: 10,
+            y: 0,
+        },
+        ProcessorLinkConfig {
+            name: "tick".into(),
+            x: 11,
+            y: 0,
+        },
+        ProcessorLinkConfig {
+            name: "frames".into(),
+            x: 12,
+            y: 0,
+        },
+        ProcessorLinkConfig {
+            name: "bank1".into(),
+            x: 13,
+            y: 0,
+        },
+        #[cfg(feature = "joystick")]
+        ProcessorLinkConfig {
+            name: "joystick".into(),
+            x: 14,
+            y: 0,
+        },
+        #[cfg(feature = "ssd1306")]
+        ProcessorLinkConfig {
+            name: "display2".into(),
+            x: 15,
+            y: 0,
+        },
+        #[cfg(feature = "ir")]
+        ProcessorLinkConfig {
+            name: "ir".into(),
+            x: 16,
+            y: 0,
+        },
+        #[cfg(feature = "temp_sensor")]
+        ProcessorLinkConfig {
+            name: "temp".into(),
+            x: 17,
+            y: 0,
+        },
+        #[cfg(feature = "selector")]
+        ProcessorLinkConfig {
+            name: "selector".into(),
+            x: 18,
+            y: 0,
+        },
+        #[cfg(feature = "neopixel")]
+        ProcessorLinkConfig {
+            name: "neopixel".into(),
+            x: 19,
+            y: 0,
+        },
+    ];
+
     let mut builder = LogicVMBuilder::new();
 
-    builder.add_buildings([
-        Building::from_processor_builder(
-            &custom_content::PROCESSOR,
-            PackedPoint2 { x: 0, y: 0 },
-            ProcessorBuilder {
-                ipt: 100.,
-                privileged: true,
-                code: deserialize_ast(AST_BYTES).unwrap().into_boxed_slice(),
-                links: &[
-                    ProcessorLinkConfig {
-                        name: "gpio".into(),
-                        x: 1,
-                        y: 0,
-                    },
-                    ProcessorLinkConfig {
-                        name: "uart0".into(),
-                        x: 2,
-                        y: 0,
-                    },
-                    ProcessorLinkConfig {
-                        name: "serial".into(),
-                        x: 3,
-                        y: 0,
-                    },
-                    ProcessorLinkConfig {
-                        name: "display1".into(),
-                        x: 4,
-                        y: 0,
-                    },
-                ],
-                instruction_hook: Some(Box::new(|instruction, _, _| {
-                    if let Instruction::Stop(_) = instruction {
-                        #[cfg(feature = "pico1")]
-                        embassy_rp::rom_data::reset_to_usb_boot(0, 0);
-
-                        #[cfg(feature = "pico2")]
-                        {
-                            // REBOOT_TYPE_BOOTSEL
-                            embassy_rp::rom_data::reboot(0x0002, 100, 0, 0);
-                            loop {
-                                core::hint::spin_loop();
-                            }
-                        }
+    let processor = Building::from_processor_builder(
+        &custom_content::PROCESSOR,
+        PackedPoint2 { x: 0, y: 0 },
+        ProcessorBuilder {
+            ipt: PROCESSOR_IPT,
+            privileged: true,
+            code: deserialize_ast(program.ast_bytes)
+                .unwrap()
+                .into_boxed_slice(),
+            links: &links,
+            instruction_hook: Some(Box::new({
+                let instruction_counter = instruction_counter.clone();
+                let mut serial_writer = serial_data.writer();
+                let auto_flush = [serial_data.auto_flush(), uart0_data.auto_flush()];
+                let display_data = display_data.clone();
+                #[cfg(feature = "profile")]
+                let profiler = profiler.clone();
+                move |instruction, state, _| {
+                    #[cfg(feature = "profile")]
+                    profiler.record(instruction);
+                    instruction_counter.increment();
+                    debug_commands.run(state, &mut serial_writer);
+                    auto_flush_lines(&auto_flush, state);
+                    display_data.borrow_mut().draw_immediate(state);
+                    match instruction {
+                        Instruction::Stop(_) => reboot_to_bootsel(),
+                        Instruction::End(_) => display_data.borrow_mut().restart(),
+                        _ => {}
                     }
                     None
-                })),
-            },
-            &builder,
+                }
+            })),
+        },
+        &builder,
+    );
+
+    // positions are recorded as the buildings are placed, so links can be checked against them
+    let mut positions = Vec::new();
+    let mut at = |x: i32, y: i32| {
+        positions.push((x, y));
+        PackedPoint2 {
+            x: x as _,
+            y: y as _,
+        }
+    };
+
+    let buildings = [
+        Building::new(&custom_content::GPIO, at(1, 0), gpio_data.into()),
+        Building::new(&custom_content::UART, at(2, 0), uart0_data.into()),
+        Building::new(&custom_content::SERIAL, at(3, 0), serial_data.into()),
+        Building::new(
+            &custom_content::ST7789VW_DISPLAY,
+            at(4, 0),
+            display_data.into(),
         ),
+        #[cfg(feature = "ultrasonic")]
         Building::new(
-            &custom_content::GPIO,
-            PackedPoint2 { x: 1, y: 0 },
-            GpioData::new([
-                gpio_data_pin!(p.PIN_2),
-                gpio_data_pin!(p.PIN_3),
-                gpio_data_pin!(p.PIN_4),
-                gpio_data_pin!(p.PIN_5),
-                gpio_data_pin!(p.PIN_6),
-                gpio_data_pin!(p.PIN_7),
-                gpio_data_pin!(p.PIN_8),
-                gpio_data_pin!(p.PIN_9),
-                (bl_pin as usize, bl),
-                gpio_data_pin!(p.PIN_16),
-                gpio_data_pin!(p.PIN_17),
-                gpio_data_pin!(p.PIN_18),
-                gpio_data_pin!(p.PIN_19),
-                gpio_data_pin!(p.PIN_20),
-                gpio_data_pin!(p.PIN_21),
-                gpio_data_pin!(p.PIN_22),
-                gpio_data_pin!(p.PIN_25),
-                gpio_data_pin!(p.PIN_26),
-                gpio_data_pin!(p.PIN_27),
-                gpio_data_pin!(p.PIN_28),
-            ])
+            &custom_content::ULTRASONIC,
+            at(5, 0),
+            UltrasonicData::new(
+                gpio::Output::new(p.PIN_2, gpio::Level::Low),
+                gpio::Input::new(p.PIN_3, gpio::Pull::None),
+            )
             .into(),
         ),
+        #[cfg(feature = "dht")]
         Building::new(
-            &custom_content::UART,
-            PackedPoint2 { x: 2, y: 0 },
-            uart0_data.into(),
+            &custom_content::DHT,
+            at(6, 0),
+            DhtData::new(gpio::Flex::new(p.PIN_4), DhtModel::Dht22).into(),
         ),
         Building::new(
-            &custom_content::SERIAL,
-            PackedPoint2 { x: 3, y: 0 },
-            serial_data.into(),
+            &custom_content::TASK_STATS,
+            at(7, 0),
+            task_stats_data.into(),
         ),
+        #[cfg(feature = "one_wire")]
         Building::new(
-            &custom_content::ST7789VW_DISPLAY,
-            PackedPoint2 { x: 4, y: 0 },
-            display_data.into(),
+            &custom_content::ONE_WIRE,
+            at(8, 0),
+            OneWireData::new(gpio::Flex::new(p.PIN_6)).into(),
         ),
-    ]);
+        Building::new(
+            &custom_content::FLASH,
+            at(9, 0),
+            FlashData::new(program.ast_bytes.len()).into(),
+        ),
+        #[cfg(feature = "retained")]
+        Building::new(
+            &custom_content::RETAINED,
+            at(10, 0),
+            RetainedData::load().into(),
+        ),
+        Building::new(
+            &custom_content::TICK,
+            at(11, 0),
+            TickData::default()
+                .with_processor_clock(processor_clock.clone())
+                .into(),
+        ),
+        Building::new(&custom_content::FRAMES, at(12, 0), frames_data.into()),
+        Building::new(
+            &custom_content::IMAGE_BANK,
+            at(13, 0),
+            image_bank_data.into(),
+        ),
+        #[cfg(feature = "joystick")]
+        Building::new(
+            &custom_content::JOYSTICK,
+            at(14, 0),
+            JoystickData::new(
+                embassy_rp::adc::Adc::new_blocking(p.ADC, Default::default()),
+                embassy_rp::adc::Channel::new_pin(p.PIN_26, gpio::Pull::None),
+                embassy_rp::adc::Channel::new_pin(p.PIN_27, gpio::Pull::None),
+                gpio::Input::new(p.PIN_22, gpio::Pull::Up),
+            )
+            .into(),
+        ),
+        #[cfg(feature = "ssd1306")]
+        Building::new(
+            &custom_content::SSD1306_DISPLAY,
+            at(15, 0),
+            oled_data.into(),
+        ),
+        #[cfg(feature = "ir")]
+        Building::new(&custom_content::IR, at(16, 0), ir_data.into()),
+        #[cfg(feature = "temp_sensor")]
+        Building::new(
+            &custom_content::TEMP_SENSOR,
+            at(17, 0),
+            TempSensorData::new(
+                embassy_rp::adc::Adc::new_blocking(p.ADC, Default::default()),
+                embassy_rp::adc::Channel::new_temp_sensor(p.ADC_TEMP_SENSOR),
+            )
+            .into(),
+        ),
+        // one pin per position, starting from GP18
+        #[cfg(feature = "selector")]
+        Building::new(
+            &custom_content::SELECTOR,
+            at(18, 0),
+            SelectorData::new(
+                [
+                    p.PIN_18.degrade(),
+                    p.PIN_19.degrade(),
+                    p.PIN_20.degrade(),
+                    p.PIN_21.degrade(),
+                ]
+                .map(|pin| gpio::Input::new(pin, gpio::Pull::Up)),
+            )
+            .into(),
+        ),
+        #[cfg(feature = "neopixel")]
+        Building::new(&custom_content::NEOPIXEL, at(19, 0), neopixel_data.into()),
+    ];
+
+    // catch typos here instead of ending up with a link that silently points at nothing
+    if let Err(err) = check_links(&links, &positions) {
+        panic!("{err}");
+    }
+
+    builder.add_buildings(iter::once(processor).chain(buildings));
 
     let mut globals = LVar::create_global_constants();
     globals.extend([
         // GPIO pin constants
+        #[cfg(not(feature = "backlight_pwm"))]
         (
             u16str!("@pinBacklight").into(),
             LVar::Constant(bl_pin.into()),
         ),
         (u16str!("@pinLED").into(), LVar::Constant(25.into())),
+        // chip number, so programs can adapt to the available RAM and CPU
+        #[cfg(feature = "pico1")]
+        (u16str!("@board").into(), LVar::Constant(2040.into())),
+        #[cfg(feature = "pico2")]
+        (u16str!("@board").into(), LVar::Constant(2350.into())),
+        (
+            u16str!("@resetReason").into(),
+            LVar::Constant((reset_reason() as usize as f64).into()),
+        ),
+        // switch positions read at boot
+        #[cfg(feature = "dip_config")]
+        (
+            u16str!("@dipConfig").into(),
+            LVar::Constant(
+                (read_dip_config([
+                    p.PIN_18.degrade(),
+                    p.PIN_19.degrade(),
+                    p.PIN_20.degrade(),
+                    p.PIN_21.degrade(),
+                ]) as f64)
+                    .into(),
+            ),
+        ),
     ]);
+    globals.extend(PROGRAMS.iter().enumerate().map(|(i, program)| {
+        (
+            U16String::from_str(program.global).into(),
+            LVar::Constant((i as f64).into()),
+        )
+    }));
+    globals.extend(
+        ConfigOption::globals()
+            .chain(Stat::globals())
+            .chain(ResetReason::globals())
+            .map(|(name, value)| (name.into(), value)),
+    );
 
     let vm = builder.build_with_globals(&globals).unwrap();
 
@@ -319,13 +885,108 @@ async fn main(spawner: Spawner) {
 
     let start = Instant::now();
     loop {
-        vm.do_tick_with_delta(start.elapsed().into(), 1.0);
+        main_loop_counter.record();
+
+        let mut idle = true;
+        for _ in 0..VM_TICKS_PER_LOOP {
+            if heap_monitor.check(&mut heap_writer) || processor_clock.sleep_remaining().is_some() {
+                break;
+            }
+            // wait instructions are checked against this, so they use real time
+            vm.do_tick_with_delta(start.elapsed().into(), 1.0);
+            processor_clock.record_tick();
+            #[cfg(feature = "profile")]
+            profiler.end_tick();
+            let ran = instruction_counter.get() > 0;
+            instruction_counter.reset();
+            // more ticks won't do anything until the processor is done waiting
+            if !ran {
+                break;
+            }
+            idle = false;
+        }
 
         uart0_tick().await;
         serial_tick().await;
+        task_stats_tick();
+        status_led.tick();
+        #[cfg(feature = "backlight_pwm")]
+        backlight_display.borrow_mut().update_backlight();
+        #[cfg(feature = "rgb_led")]
+        rgb_led.set_state(if idle {
+            FirmwareState::Waiting
+        } else {
+            FirmwareState::Running
+        });
+
+        // pixel streams are drawn straight to the display, so count their failures with mlog's
+        draw_errors.record(pixel_stream.draw(&mut display));
 
         // let other threads do things before we continue
-        yield_now().await;
+        if idle {
+            // nothing ran (eg. the processor is in a wait), so sleep instead of spinning, but
+            // not past the end of a sleep from mlog
+            let sleep = processor_clock
+                .sleep_remaining()
+                .map_or(IDLE_SLEEP, |remaining| remaining.min(IDLE_SLEEP));
+            Timer::after(sleep).await;
+        } else {
+            yield_now().await;
+        }
+    }
+}
+
+#[cfg(all(feature = "panic_bootsel", feature = "panic_halt"))]
+compile_error!("features \"panic_bootsel\" and \"panic_halt\" are mutually exclusive");
+
+#[cfg(all(feature = "framebuffer_rgb666", feature = "pico1"))]
+compile_error!("feature \"framebuffer_rgb666\" doesn't fit in the pico1's RAM");
+
+#[cfg(all(feature = "layers", feature = "pico1"))]
+compile_error!("feature \"layers\" doesn't fit in the pico1's RAM");
+
+#[cfg(all(feature = "layers", feature = "framebuffer_rgb666"))]
+compile_error!("features \"layers\" and \"framebuffer_rgb666\" don't fit in RAM together");
+
+#[cfg(all(feature = "pulse_in", feature = "ultrasonic"))]
+compile_error!("features \"pulse_in\" and \"ultrasonic\" both use GP3");
+
+#[cfg(all(feature = "adc", feature = "joystick"))]
+compile_error!("features \"adc\" and \"joystick\" both use the ADC and GP26-GP27");
+
+#[cfg(all(feature = "adc", feature = "ir"))]
+compile_error!("features \"adc\" and \"ir\" both use GP28");
+
+#[cfg(all(feature = "joystick", feature = "neopixel"))]
+compile_error!("features \"joystick\" and \"neopixel\" both use GP22");
+
+#[cfg(all(feature = "dip_config", feature = "selector"))]
+compile_error!("features \"dip_config\" and \"selector\" both use GP18-GP21");
+
+#[cfg(all(feature = "adc", feature = "temp_sensor"))]
+compile_error!("features \"adc\" and \"temp_sensor\" both use the ADC");
+
+#[cfg(all(feature = "joystick", feature = "temp_sensor"))]
+compile_error!("features \"joystick\" and \"temp_sensor\" both use the ADC");
+
+/// Called on the boot after a panic, once the panic message has been written to UART.
+fn recover_from_panic() -> ! {
+    #[cfg(feature = "panic_bootsel")]
+    reboot_to_bootsel();
+
+    #[cfg(feature = "panic_halt")]
+    loop {
+        cortex_m::asm::wfi();
+    }
+
+    #[cfg(not(any(feature = "panic_bootsel", feature = "panic_halt")))]
+    cortex_m::peripheral::SCB::sys_reset();
+}
+
+const fn parse_env_u16(value: &str) -> u16 {
+    match u16::from_str_radix(value, 10) {
+        Ok(value) => value,
+        Err(_) => panic!("expected an integer"),
     }
 }
 
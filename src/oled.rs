@@ -0,0 +1,67 @@
+use embedded_graphics::{
+    Pixel,
+    pixelcolor::{BinaryColor, Rgb888},
+    prelude::*,
+    primitives::Rectangle,
+};
+use embedded_hal::i2c::I2c;
+use ssd1306::{I2CDisplayInterface, Ssd1306, mode::BufferedGraphicsMode, prelude::*};
+
+type Driver<I> =
+    Ssd1306<I2CInterface<I>, DisplaySize128x64, BufferedGraphicsMode<DisplaySize128x64>>;
+
+/// 128x64 SSD1306 OLED on I2C, as a draw target for a second
+/// [`DisplayData`](crate::buildings::DisplayData).
+///
+/// Drawing only changes the driver's buffer in RAM, so the display has to be flushed with
+/// [`Oled::flush`] to show it. Colors are converted to on or off by their brightness.
+pub struct Oled<I>(Driver<I>);
+
+impl<I: I2c> Oled<I> {
+    pub fn new(i2c: I) -> Result<Self, <Driver<I> as DrawTarget>::Error> {
+        let mut display = Ssd1306::new(
+            I2CDisplayInterface::new(i2c),
+            DisplaySize128x64,
+            DisplayRotation::Rotate0,
+        )
+        .into_buffered_graphics_mode();
+        display.init()?;
+        Ok(Self(display))
+    }
+
+    pub fn flush(&mut self) -> Result<(), <Driver<I> as DrawTarget>::Error> {
+        self.0.flush()
+    }
+}
+
+fn to_binary(color: Rgb888) -> BinaryColor {
+    // approximate luma, weighted like Rec. 601
+    let luma = (color.r() as u32 * 77 + color.g() as u32 * 150 + color.b() as u32 * 29) >> 8;
+    (luma >= 128).into()
+}
+
+impl<I: I2c> OriginDimensions for Oled<I> {
+    fn size(&self) -> Size {
+        self.0.size()
+    }
+}
+
+impl<I: I2c> DrawTarget for Oled<I> {
+    type Color = Rgb888;
+    type Error = <Driver<I> as DrawTarget>::Error;
+
+    fn draw_iter<T>(&mut self, pixels: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.0.draw_iter(
+            pixels
+                .into_iter()
+                .map(|Pixel(point, color)| Pixel(point, to_binary(color))),
+        )
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.0.fill_solid(area, to_binary(color))
+    }
+}
@@ -0,0 +1,192 @@
+use alloc::{rc::Rc, vec::Vec};
+use core::cell::RefCell;
+
+use embedded_graphics::{
+    pixelcolor::{Rgb565, Rgb888, raw::RawU16},
+    prelude::*,
+    primitives::Rectangle,
+};
+
+// "PX", format, then x, y, width, height as little endian u16s
+const HEADER_MAGIC: &[u8] = b"PX";
+const HEADER_SIZE: usize = 11;
+
+// the serial task stops receiving once this many bytes are waiting to be drawn
+const MAX_PENDING_BYTES: usize = 4096;
+
+#[derive(Debug, Clone, Copy)]
+enum PixelFormat {
+    /// Big endian.
+    Rgb565,
+    Rgb888,
+}
+
+impl PixelFormat {
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Self::Rgb565),
+            1 => Some(Self::Rgb888),
+            _ => None,
+        }
+    }
+
+    fn bytes_per_pixel(self) -> usize {
+        match self {
+            Self::Rgb565 => 2,
+            Self::Rgb888 => 3,
+        }
+    }
+
+    fn decode(self, bytes: &[u8]) -> Rgb888 {
+        match self {
+            Self::Rgb565 => {
+                Rgb565::from(RawU16::new(u16::from_be_bytes([bytes[0], bytes[1]]))).into()
+            }
+            Self::Rgb888 => Rgb888::new(bytes[0], bytes[1], bytes[2]),
+        }
+    }
+}
+
+struct Frame {
+    area: Rectangle,
+    format: PixelFormat,
+    bytes_left: usize,
+    rows_drawn: u32,
+}
+
+#[derive(Default)]
+struct State {
+    enabled: bool,
+    // frames that don't overlap this are ignored
+    bounds: Rectangle,
+    frame: Option<Frame>,
+    pending: Vec<u8>,
+}
+
+/// Raw pixel frames streamed from the host over USB serial, bypassing mlog.
+///
+/// Each frame starts with an 11 byte header: `PX`, the pixel format (0 for big endian Rgb565, 1
+/// for Rgb888), then the x, y, width, and height of the region to draw as little endian u16s,
+/// with the origin at the top left of the display. The header must be at the start of a USB
+/// packet, and must be followed by exactly `width * height` pixels in row-major order. Frames
+/// that don't overlap the display are passed through to mlog like any other packet.
+#[derive(Clone)]
+pub struct PixelStream(Rc<RefCell<State>>);
+
+impl PixelStream {
+    pub fn new(bounds: Rectangle) -> Self {
+        Self(Rc::new(RefCell::new(State {
+            bounds,
+            ..Default::default()
+        })))
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        let mut state = self.0.borrow_mut();
+        state.enabled = enabled;
+        if !enabled {
+            state.frame = None;
+            state.pending = Vec::new();
+        }
+    }
+
+    /// Consumes a packet received over serial if it's part of a frame.
+    ///
+    /// Returns false if the packet should be passed through to mlog instead.
+    pub fn receive(&self, mut data: &[u8]) -> bool {
+        let mut state = self.0.borrow_mut();
+        if !state.enabled {
+            return false;
+        }
+
+        if state.frame.is_none() {
+            if data.len() < HEADER_SIZE || !data.starts_with(HEADER_MAGIC) {
+                return false;
+            }
+
+            let Some(format) = PixelFormat::from_byte(data[2]) else {
+                return false;
+            };
+            let [x, y, width, height] =
+                [3, 5, 7, 9].map(|i| u16::from_le_bytes([data[i], data[i + 1]]) as u32);
+
+            let area = Rectangle::new(Point::new(x as i32, y as i32), Size::new(width, height));
+            if area.intersection(&state.bounds).is_zero_sized() {
+                return false;
+            }
+            // too big for usize on a 32-bit target if the header is garbage
+            let Some(bytes) = (width as usize)
+                .checked_mul(height as usize)
+                .and_then(|pixels| pixels.checked_mul(format.bytes_per_pixel()))
+            else {
+                return false;
+            };
+
+            state.frame = Some(Frame {
+                area,
+                format,
+                bytes_left: bytes,
+                rows_drawn: 0,
+            });
+            data = &data[HEADER_SIZE..];
+        }
+
+        let State { frame, pending, .. } = &mut *state;
+        if let Some(frame) = frame {
+            // anything past the end of the frame is dropped
+            let n = data.len().min(frame.bytes_left);
+            pending.extend_from_slice(&data[..n]);
+            frame.bytes_left -= n;
+        }
+        true
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.0.borrow().pending.len() >= MAX_PENDING_BYTES
+    }
+
+    /// Draws all complete rows that have been received so far. Rows that fail to draw are dropped.
+    pub fn draw<D>(&self, display: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget,
+        D::Color: From<Rgb888>,
+    {
+        let mut state = self.0.borrow_mut();
+        let State { frame, pending, .. } = &mut *state;
+        let Some(current) = frame else {
+            return Ok(());
+        };
+
+        let bytes_per_pixel = current.format.bytes_per_pixel();
+        let row_bytes = current.area.size.width as usize * bytes_per_pixel;
+        let rows = if row_bytes > 0 {
+            pending.len() / row_bytes
+        } else {
+            0
+        };
+
+        let mut result = Ok(());
+        if rows > 0 {
+            let area = Rectangle::new(
+                current.area.top_left + Point::new(0, current.rows_drawn as i32),
+                Size::new(current.area.size.width, rows as u32),
+            );
+            let format = current.format;
+            result = display.fill_contiguous(
+                &area,
+                pending[..rows * row_bytes]
+                    .chunks_exact(bytes_per_pixel)
+                    .map(|bytes| format.decode(bytes).into()),
+            );
+
+            // rows that failed are skipped, so the rest of the frame still lines up
+            pending.drain(..rows * row_bytes);
+            current.rows_drawn += rows as u32;
+        }
+
+        if current.bytes_left == 0 && pending.is_empty() {
+            *frame = None;
+        }
+        result
+    }
+}
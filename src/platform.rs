@@ -0,0 +1,90 @@
+use embassy_rp::{
+    pac,
+    peripherals::WATCHDOG,
+    watchdog::{self, Watchdog},
+};
+
+use crate::buildings::ResetReason;
+
+// upper half of the watchdog scratch register, so a random value isn't mistaken for a request
+const PROGRAM_REQUEST_MAGIC: u32 = 0x6d6c_0000;
+
+fn watchdog() -> Watchdog {
+    // the watchdog is only used for its scratch registers and to reset, so sharing it is fine
+    Watchdog::new(unsafe { WATCHDOG::steal() })
+}
+
+/// Returns the index in `PROGRAMS` requested by [`reboot_to_program`] before the last reset, if
+/// any, and clears the request.
+pub fn take_requested_program() -> Option<usize> {
+    let mut watchdog = watchdog();
+    let value = watchdog.get_scratch(0);
+    watchdog.set_scratch(0, 0);
+    (value & 0xffff_0000 == PROGRAM_REQUEST_MAGIC).then_some((value & 0xffff) as usize)
+}
+
+/// Returns why the chip last reset.
+///
+/// The RP2040 can't tell a brownout apart from a power cycle, so brownouts are reported as
+/// [`ResetReason::PowerOn`] on a Pico 1.
+pub fn reset_reason() -> ResetReason {
+    match watchdog().reset_reason() {
+        Some(watchdog::ResetReason::TimedOut) => return ResetReason::Watchdog,
+        // eg. reboot_to_program
+        Some(watchdog::ResetReason::Forced) => return ResetReason::SoftReset,
+        None => {}
+    }
+
+    #[cfg(feature = "pico1")]
+    {
+        let chip_reset = pac::VREG_AND_CHIP_RESET.chip_reset().read();
+        if chip_reset.had_run() {
+            ResetReason::RunPin
+        } else if chip_reset.had_psm_restart() {
+            ResetReason::Debugger
+        } else {
+            ResetReason::PowerOn
+        }
+    }
+
+    #[cfg(feature = "pico2")]
+    {
+        let chip_reset = pac::POWMAN.chip_reset().read();
+        if chip_reset.had_bor() {
+            ResetReason::Brownout
+        } else if chip_reset.had_run_low() {
+            ResetReason::RunPin
+        } else if chip_reset.had_dp_reset_req() || chip_reset.had_rescue() {
+            ResetReason::Debugger
+        } else {
+            ResetReason::PowerOn
+        }
+    }
+}
+
+/// Reboots into the program at `index` in `PROGRAMS`.
+///
+/// The request is stored in a watchdog scratch register, which isn't cleared by a soft reset.
+pub fn reboot_to_program(index: usize) -> ! {
+    let mut watchdog = watchdog();
+    watchdog.set_scratch(0, PROGRAM_REQUEST_MAGIC | index as u32);
+    watchdog.trigger_reset();
+
+    loop {
+        core::hint::spin_loop();
+    }
+}
+
+/// Reboots into the USB bootloader, so new firmware can be flashed without holding BOOTSEL.
+pub fn reboot_to_bootsel() -> ! {
+    #[cfg(feature = "pico1")]
+    embassy_rp::rom_data::reset_to_usb_boot(0, 0);
+
+    // REBOOT_TYPE_BOOTSEL
+    #[cfg(feature = "pico2")]
+    embassy_rp::rom_data::reboot(0x0002, 100, 0, 0);
+
+    loop {
+        core::hint::spin_loop();
+    }
+}
@@ -0,0 +1,110 @@
+use alloc::{format, rc::Rc, string::String, vec::Vec};
+use core::{
+    cell::RefCell,
+    fmt::Write,
+    mem::{Discriminant, discriminant},
+};
+
+use embassy_time::{Duration, Instant};
+use mindy::vm::instructions::Instruction;
+
+struct Entry {
+    opcode: Discriminant<Instruction>,
+    name: String,
+    count: u64,
+    time: Duration,
+}
+
+#[derive(Default)]
+struct State {
+    entries: Vec<Entry>,
+    // the instruction that's currently running, and when it started
+    current: Option<(usize, Instant)>,
+}
+
+/// Shared record of the time spent running each kind of instruction.
+///
+/// Instructions are timed from one instruction hook call to the next, so each instruction's time
+/// includes the overhead of the hook itself.
+#[derive(Clone, Default)]
+pub struct Profiler(Rc<RefCell<State>>);
+
+impl Profiler {
+    /// Called from the instruction hook before each instruction runs.
+    pub fn record(&self, instruction: &Instruction) {
+        let now = Instant::now();
+        let mut state = self.0.borrow_mut();
+        state.finish(now);
+
+        let opcode = discriminant(instruction);
+        let index = match state
+            .entries
+            .iter()
+            .position(|entry| entry.opcode == opcode)
+        {
+            Some(index) => index,
+            None => {
+                // only format the instruction the first time we see its opcode, since it's slow
+                let name = format!("{instruction:?}");
+                let len = name
+                    .find(|c: char| !c.is_alphanumeric())
+                    .unwrap_or(name.len());
+                state.entries.push(Entry {
+                    opcode,
+                    name: name[..len].into(),
+                    count: 0,
+                    time: Duration::from_ticks(0),
+                });
+                state.entries.len() - 1
+            }
+        };
+        state.current = Some((index, Instant::now()));
+    }
+
+    /// Stops timing the last instruction, so the time between ticks isn't counted.
+    pub fn end_tick(&self) {
+        self.0.borrow_mut().finish(Instant::now());
+    }
+
+    /// Writes the time spent per opcode to `writer`, slowest first.
+    pub fn dump(&self, writer: &mut impl Write) {
+        let mut state = self.0.borrow_mut();
+        // don't count the time spent writing the profile
+        state.current = None;
+
+        let total = state
+            .entries
+            .iter()
+            .map(|entry| entry.time.as_micros())
+            .sum::<u64>()
+            .max(1);
+
+        state.entries.sort_unstable_by_key(|entry| entry.time);
+        for entry in state.entries.iter().rev() {
+            let micros = entry.time.as_micros();
+            writeln!(
+                writer,
+                "{}: {} runs, {micros} us ({}%), {} ns avg",
+                entry.name,
+                entry.count,
+                micros * 100 / total,
+                micros * 1000 / entry.count.max(1),
+            )
+            .ok();
+        }
+    }
+
+    pub fn reset(&self) {
+        *self.0.borrow_mut() = State::default();
+    }
+}
+
+impl State {
+    fn finish(&mut self, now: Instant) {
+        if let Some((index, start)) = self.current.take() {
+            let entry = &mut self.entries[index];
+            entry.count += 1;
+            entry.time += now - start;
+        }
+    }
+}
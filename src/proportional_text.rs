@@ -0,0 +1,98 @@
+use embedded_graphics::{
+    mono_font::MonoTextStyle,
+    prelude::*,
+    primitives::Rectangle,
+    text::{
+        Baseline,
+        renderer::{TextMetrics, TextRenderer},
+    },
+};
+
+// (first column with ink, number of columns with ink) of each printable ASCII glyph in FONT_6X13,
+// measured by the build script
+include!(concat!(env!("OUT_DIR"), "/glyph_ink.rs"));
+
+// blank columns between glyphs
+const GLYPH_SPACING: i32 = 1;
+
+// characters that aren't printable ASCII are replaced before drawing, so this is just a fallback
+const FONT_WIDTH: i32 = 6;
+
+/// Returns where a glyph's ink starts in its cell, and how far to advance past it.
+fn glyph_metrics(c: char) -> (i32, i32) {
+    match GLYPH_INK.get((c as usize).wrapping_sub(' ' as usize)) {
+        Some(&(left, width)) => (left.into(), i32::from(width) + GLYPH_SPACING),
+        None => (0, FONT_WIDTH),
+    }
+}
+
+/// Text renderer that draws a mono font's glyphs only as wide as their ink, like Mindustry's
+/// proportional font, instead of giving each one the whole cell.
+#[derive(Clone, Copy)]
+pub struct ProportionalTextStyle<C>(MonoTextStyle<'static, C>);
+
+impl<C> ProportionalTextStyle<C> {
+    pub fn new(style: MonoTextStyle<'static, C>) -> Self {
+        Self(style)
+    }
+}
+
+impl<C: PixelColor> TextRenderer for ProportionalTextStyle<C> {
+    type Color = C;
+
+    fn draw_string<D>(
+        &self,
+        text: &str,
+        mut position: Point,
+        baseline: Baseline,
+        target: &mut D,
+    ) -> Result<Point, D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        let mut buf = [0; 4];
+        for c in text.chars() {
+            let (left, advance) = glyph_metrics(c);
+            // the cell is moved left so that its ink starts at the position
+            self.0.draw_string(
+                c.encode_utf8(&mut buf),
+                position - Point::new(left, 0),
+                baseline,
+                target,
+            )?;
+            position.x += advance;
+        }
+        Ok(position)
+    }
+
+    fn draw_whitespace<D>(
+        &self,
+        width: u32,
+        position: Point,
+        baseline: Baseline,
+        target: &mut D,
+    ) -> Result<Point, D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        self.0.draw_whitespace(width, position, baseline, target)
+    }
+
+    fn measure_string(&self, text: &str, position: Point, baseline: Baseline) -> TextMetrics {
+        // without the spacing after the last glyph, so alignment matches the ink
+        let width = (text.chars().map(|c| glyph_metrics(c).1).sum::<i32>() - GLYPH_SPACING).max(0);
+        // the mono style knows where the baseline puts the top of the line
+        let line = self.0.measure_string("", position, baseline).bounding_box;
+        TextMetrics {
+            bounding_box: Rectangle::new(
+                line.top_left,
+                Size::new(width as u32, self.0.font.character_size.height),
+            ),
+            next_position: position + Point::new(width, 0),
+        }
+    }
+
+    fn line_height(&self) -> u32 {
+        self.0.line_height()
+    }
+}
@@ -0,0 +1,69 @@
+use embassy_rp::pwm::PwmOutput;
+use embedded_hal::pwm::SetDutyCycle;
+
+// full brightness is uncomfortable to look at on most bare RGB LEDs
+const BRIGHTNESS_PERCENT: u8 = 25;
+
+/// What the firmware is doing, shown on the [`RgbLed`].
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FirmwareState {
+    /// Green: the processor is running instructions.
+    Running,
+    /// Blue: the processor has nothing to do (eg. it's in a `wait`).
+    Waiting,
+    /// Red: the previous boot panicked, and the panic message is being reported.
+    PanicRecovered,
+}
+
+impl FirmwareState {
+    fn color(self) -> (bool, bool, bool) {
+        match self {
+            Self::Running => (false, true, false),
+            Self::Waiting => (false, false, true),
+            Self::PanicRecovered => (true, false, false),
+        }
+    }
+}
+
+/// External RGB LED that shows the firmware's state by color, with each channel driven by PWM.
+///
+/// Unlike the [`StatusLed`](crate::status_led::StatusLed), this can't be used by mlog.
+pub struct RgbLed<'a> {
+    red: PwmOutput<'a>,
+    green: PwmOutput<'a>,
+    blue: PwmOutput<'a>,
+    state: Option<FirmwareState>,
+}
+
+impl<'a> RgbLed<'a> {
+    /// Creates an RGB LED that starts off. Each output should be high to turn its channel on.
+    pub fn new(red: PwmOutput<'a>, green: PwmOutput<'a>, blue: PwmOutput<'a>) -> Self {
+        let mut led = Self {
+            red,
+            green,
+            blue,
+            state: None,
+        };
+        led.apply((false, false, false));
+        led
+    }
+
+    pub fn set_state(&mut self, state: FirmwareState) {
+        if self.state != Some(state) {
+            self.state = Some(state);
+            self.apply(state.color());
+        }
+    }
+
+    fn apply(&mut self, (red, green, blue): (bool, bool, bool)) {
+        for (output, on) in [
+            (&mut self.red, red),
+            (&mut self.green, green),
+            (&mut self.blue, blue),
+        ] {
+            output
+                .set_duty_cycle_percent(if on { BRIGHTNESS_PERCENT } else { 0 })
+                .ok();
+        }
+    }
+}
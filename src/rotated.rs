@@ -0,0 +1,83 @@
+use embedded_graphics::{Pixel, prelude::*, primitives::Rectangle};
+
+/// Drawable wrapper that rotates another drawable by a multiple of 90 degrees counterclockwise
+/// about a point, eg. for vertical text, which embedded-graphics can't draw by itself.
+pub struct Rotated<'a, D> {
+    drawable: &'a D,
+    center: Point,
+    quarter_turns: u8,
+}
+
+impl<'a, D> Rotated<'a, D> {
+    pub fn new(drawable: &'a D, center: Point, quarter_turns: u8) -> Self {
+        Self {
+            drawable,
+            center,
+            quarter_turns: quarter_turns % 4,
+        }
+    }
+}
+
+impl<D: Drawable> Drawable for Rotated<'_, D> {
+    type Color = D::Color;
+    type Output = ();
+
+    fn draw<T>(&self, target: &mut T) -> Result<(), T::Error>
+    where
+        T: DrawTarget<Color = Self::Color>,
+    {
+        if self.quarter_turns == 0 {
+            self.drawable.draw(target)?;
+        } else {
+            self.drawable.draw(&mut RotatedDrawTarget {
+                inner: target,
+                center: self.center,
+                quarter_turns: self.quarter_turns,
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Passes each pixel to the inner draw target rotated about `center`. Rectangles aren't rotated
+/// as a whole, so everything goes through `draw_iter`.
+struct RotatedDrawTarget<'a, T> {
+    inner: &'a mut T,
+    center: Point,
+    quarter_turns: u8,
+}
+
+impl<T: Dimensions> Dimensions for RotatedDrawTarget<'_, T> {
+    fn bounding_box(&self) -> Rectangle {
+        self.inner.bounding_box()
+    }
+}
+
+impl<T: DrawTarget> DrawTarget for RotatedDrawTarget<'_, T> {
+    type Color = T::Color;
+    type Error = T::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let (center, quarter_turns) = (self.center, self.quarter_turns);
+        self.inner.draw_iter(
+            pixels
+                .into_iter()
+                .map(|Pixel(point, color)| Pixel(rotate(point, center, quarter_turns), color)),
+        )
+    }
+}
+
+fn rotate(point: Point, center: Point, quarter_turns: u8) -> Point {
+    // y points down, so these look counterclockwise on the display
+    let offset = point - center;
+    center
+        + match quarter_turns {
+            1 => Point::new(offset.y, -offset.x),
+            2 => Point::new(-offset.x, -offset.y),
+            3 => Point::new(-offset.y, offset.x),
+            _ => offset,
+        }
+}
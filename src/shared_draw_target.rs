@@ -0,0 +1,52 @@
+use alloc::rc::Rc;
+use core::cell::RefCell;
+
+use embedded_graphics::{Pixel, prelude::*, primitives::Rectangle};
+
+/// Draw target that can be shared between multiple owners.
+pub struct SharedDrawTarget<D>(Rc<RefCell<D>>);
+
+impl<D> SharedDrawTarget<D> {
+    pub fn new(display: D) -> Self {
+        Self(Rc::new(RefCell::new(display)))
+    }
+}
+
+impl<D> Clone for SharedDrawTarget<D> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<D: Dimensions> Dimensions for SharedDrawTarget<D> {
+    fn bounding_box(&self) -> Rectangle {
+        self.0.borrow().bounding_box()
+    }
+}
+
+impl<D: DrawTarget> DrawTarget for SharedDrawTarget<D> {
+    type Color = D::Color;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.0.borrow_mut().draw_iter(pixels)
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        self.0.borrow_mut().fill_contiguous(area, colors)
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.0.borrow_mut().fill_solid(area, color)
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.0.borrow_mut().clear(color)
+    }
+}
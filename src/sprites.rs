@@ -0,0 +1,7 @@
+//! Sprite sheet packed into flash by the build script from `src/sprites.png`, for
+//! `control config display1 @drawSprite`.
+//!
+//! Each sprite is `SPRITE_WIDTH` pixels wide, stored as runs of `(length, packed color)` in the
+//! same order as `bank1` images, so they're drawn straight from flash without using any RAM.
+
+include!(concat!(env!("OUT_DIR"), "/sprites.rs"));
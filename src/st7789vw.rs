@@ -1,3 +1,6 @@
+#[cfg(feature = "display_rgb565")]
+use embedded_graphics::pixelcolor::Rgb565;
+#[cfg(not(feature = "display_rgb565"))]
 use embedded_graphics::pixelcolor::Rgb666;
 use embedded_hal::delay::DelayNs;
 use mipidsi::{
@@ -13,11 +16,18 @@ use mipidsi::{
 // copied from mipidsi::models::ST7789 to change the color format
 // https://github.com/almindor/mipidsi/blob/d85192a933623d6c069f22d5738e25c368f55808/src/models/st7789.rs
 
-/// ST7789VW display in Rgb666 color mode.
+/// Color format sent to the display. Rgb565 is 2 bytes per pixel instead of 3, so full-screen
+/// writes are faster at the cost of color depth.
+#[cfg(not(feature = "display_rgb565"))]
+pub type DisplayColor = Rgb666;
+#[cfg(feature = "display_rgb565")]
+pub type DisplayColor = Rgb565;
+
+/// ST7789VW display in the [`DisplayColor`] color mode.
 pub struct ST7789VW;
 
 impl Model for ST7789VW {
-    type ColorFormat = Rgb666;
+    type ColorFormat = DisplayColor;
     const FRAMEBUFFER_SIZE: (u16, u16) = (240, 320);
 
     fn init<DELAY, DI>(
@@ -0,0 +1,52 @@
+use alloc::rc::Rc;
+use core::cell::RefCell;
+
+use embassy_rp::gpio::{Flex, Level};
+use embassy_time::{Duration, Instant};
+
+const HEARTBEAT_PERIOD: Duration = Duration::from_secs(1);
+const HEARTBEAT_ON: Duration = Duration::from_millis(100);
+
+struct State {
+    pin: Flex<'static>,
+    claimed: bool,
+}
+
+/// LED shared between the firmware and mlog.
+///
+/// mlog controls it like any other pin by default. Once mlog releases it with
+/// `control config gpio @claimLed 0`, the firmware blinks a heartbeat on it to show that the VM is
+/// running, until mlog claims it again.
+#[derive(Clone)]
+pub struct StatusLed(Rc<RefCell<State>>);
+
+impl StatusLed {
+    pub fn new(pin: Flex<'static>) -> Self {
+        Self(Rc::new(RefCell::new(State { pin, claimed: true })))
+    }
+
+    pub fn set_claimed(&self, claimed: bool) {
+        let mut state = self.0.borrow_mut();
+        state.claimed = claimed;
+        if !claimed {
+            // mlog may have left it as an input
+            state.pin.set_as_output();
+        }
+    }
+
+    /// Runs `f` with the pin if mlog has claimed it.
+    pub fn with_pin<R>(&self, f: impl FnOnce(&mut Flex<'static>) -> R) -> Option<R> {
+        let mut state = self.0.borrow_mut();
+        state.claimed.then(|| f(&mut state.pin))
+    }
+
+    /// Updates the heartbeat, if mlog hasn't claimed the LED.
+    pub fn tick(&self) {
+        let mut state = self.0.borrow_mut();
+        if !state.claimed {
+            let on =
+                Instant::now().as_ticks() % HEARTBEAT_PERIOD.as_ticks() < HEARTBEAT_ON.as_ticks();
+            state.pin.set_level(Level::from(on));
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use alloc::rc::Rc;
+use core::{cell::Cell, future::poll_fn, pin::pin};
+
+use embassy_time::{Duration, Instant};
+
+struct State {
+    polls: Cell<u32>,
+    last_poll: Cell<Instant>,
+    longest_gap: Cell<Duration>,
+}
+
+/// Shared count of how often a task has been polled since the last sample.
+#[derive(Clone)]
+pub struct TaskCounter(Rc<State>);
+
+impl Default for TaskCounter {
+    fn default() -> Self {
+        Self(Rc::new(State {
+            polls: Cell::new(0),
+            last_poll: Cell::new(Instant::now()),
+            longest_gap: Cell::new(Duration::from_ticks(0)),
+        }))
+    }
+}
+
+impl TaskCounter {
+    pub fn record(&self) {
+        let now = Instant::now();
+        let gap = now - self.0.last_poll.replace(now);
+        if gap > self.0.longest_gap.get() {
+            self.0.longest_gap.set(gap);
+        }
+        self.0.polls.set(self.0.polls.get().saturating_add(1));
+    }
+
+    /// Returns the number of polls and the longest time between polls since the last sample.
+    pub fn sample(&self) -> (u32, Duration) {
+        // a task that hasn't been polled at all is starving for the whole sample
+        let gap = self
+            .0
+            .last_poll
+            .get()
+            .elapsed()
+            .max(self.0.longest_gap.get());
+        self.0.longest_gap.set(Duration::from_ticks(0));
+        (self.0.polls.replace(0), gap)
+    }
+}
+
+/// Runs a future, recording each time it's polled.
+pub async fn count_polls<F: Future>(future: F, counter: TaskCounter) -> F::Output {
+    let mut future = pin!(future);
+    poll_fn(|cx| {
+        counter.record();
+        future.as_mut().poll(cx)
+    })
+    .await
+}